@@ -0,0 +1,53 @@
+use std::collections::HashMap;
+
+use crate::{Command, Tile, WideTile};
+
+/// Rewrite `alpha_masks` to contain only the alpha masks sampled by the commands of `wide_tiles`,
+/// storing identical alpha masks once, and update the commands' alpha mask indices.
+///
+/// Besides whole alpha masks, the alpha mask of every single tile stored is indexed, such that a
+/// command sampling one tile can reuse a tile within a wider alpha mask. `scratch` is used to
+/// build the compacted alpha masks, and holds the previous alpha masks afterwards.
+///
+/// The alpha masks are only deduplicated, not encoded more compactly: an encoding such as 4 bits
+/// per pixel would change what `Sample::alpha_idx` points to, and require decoding in both the CPU
+/// rasterizer and the wgpu shader. The common patterns, such as the fully opaque tiles of strips,
+/// are already stored once by deduplicating.
+pub(crate) fn compact_alpha_masks(
+    wide_tiles: &mut [WideTile],
+    alpha_masks: &mut Vec<u8>,
+    scratch: &mut Vec<u8>,
+) {
+    const TILE_MASK_LEN: usize = Tile::WIDTH as usize * Tile::HEIGHT as usize;
+
+    scratch.clear();
+    // The index into the compacted alpha masks of every alpha mask stored.
+    let mut stored: HashMap<&[u8], u32> = HashMap::new();
+    for wide_tile in wide_tiles.iter_mut() {
+        for command in &mut wide_tile.commands {
+            let Command::Sample(sample) = command else {
+                continue;
+            };
+            let start = sample.alpha_idx as usize;
+            let alpha_mask = &alpha_masks[start..start + sample.width as usize * TILE_MASK_LEN];
+            sample.alpha_idx = *stored.entry(alpha_mask).or_insert_with(|| {
+                #[expect(
+                    clippy::cast_possible_truncation,
+                    reason = "the compacted alpha masks are no larger than the alpha masks"
+                )]
+                let alpha_idx = scratch.len() as u32;
+                scratch.extend_from_slice(alpha_mask);
+                alpha_idx
+            });
+            if sample.width > 1 {
+                let tile_alpha_idxs = (sample.alpha_idx..).step_by(TILE_MASK_LEN);
+                for (tile_mask, alpha_idx) in
+                    alpha_mask.chunks_exact(TILE_MASK_LEN).zip(tile_alpha_idxs)
+                {
+                    stored.entry(tile_mask).or_insert(alpha_idx);
+                }
+            }
+        }
+    }
+    std::mem::swap(alpha_masks, scratch);
+}
@@ -0,0 +1,90 @@
+use kurbo::{Affine, PathEl, Rect};
+
+/// How much of a bounding box is within the viewport.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Visibility {
+    /// The bounding box is entirely outside the viewport.
+    Outside,
+    /// The bounding box is partially within the viewport, or is not finite.
+    Partial,
+    /// The bounding box is entirely within the viewport.
+    Inside,
+}
+
+/// Determine how much of `bbox` is within the viewport of `width` by `height` pixels.
+///
+/// Non-finite bounding boxes are never culled, such that non-finite geometry is still diagnosed.
+pub(crate) fn visibility(bbox: Rect, width: u16, height: u16) -> Visibility {
+    let (width, height) = (f64::from(width), f64::from(height));
+    if !bbox.is_finite() {
+        Visibility::Partial
+    } else if bbox.x1 <= 0. || bbox.y1 <= 0. || bbox.x0 >= width || bbox.y0 >= height {
+        Visibility::Outside
+    } else if bbox.x0 >= 0. && bbox.y0 >= 0. && bbox.x1 <= width && bbox.y1 <= height {
+        Visibility::Inside
+    } else {
+        Visibility::Partial
+    }
+}
+
+/// The distance a stroke can extend beyond its path, in the path's coordinate system.
+///
+/// This accounts for the stroke's width, miter joins and square caps.
+pub(crate) fn stroke_inflation(style: &kurbo::Stroke) -> f64 {
+    let mut scale = 1_f64;
+    if style.join == kurbo::Join::Miter {
+        scale = scale.max(style.miter_limit);
+    }
+    if style.start_cap == kurbo::Cap::Square || style.end_cap == kurbo::Cap::Square {
+        scale = scale.max(std::f64::consts::SQRT_2);
+    }
+    0.5 * style.width.abs() * scale
+}
+
+/// Append the subpaths of `path` that can cover pixels within the viewport of `width` by `height`
+/// pixels to `visible`, returning the number of subpaths culled.
+///
+/// A subpath is culled if the bounding box of its control points, inflated by `inflation` and
+/// transformed by `transform`, is outside the viewport. As subpaths are closed when filled, and
+/// strokes do not extend beyond the inflation, culled subpaths do not contribute to the winding
+/// number or coverage of any pixel within the viewport.
+pub(crate) fn cull_subpaths(
+    path: impl IntoIterator<Item = PathEl>,
+    transform: Affine,
+    inflation: f64,
+    width: u16,
+    height: u16,
+    visible: &mut Vec<PathEl>,
+) -> usize {
+    let mut culled = 0;
+    let mut subpath_start = visible.len();
+    let mut bbox: Option<Rect> = None;
+    let mut end_subpath = |visible: &mut Vec<PathEl>, bbox: Option<Rect>, start: usize| {
+        if let Some(bbox) = bbox {
+            let bbox = transform.transform_rect_bbox(bbox.inflate(inflation, inflation));
+            if visibility(bbox, width, height) == Visibility::Outside {
+                visible.truncate(start);
+                culled += 1;
+            }
+        }
+    };
+
+    for element in path {
+        if let PathEl::MoveTo(_) = element {
+            end_subpath(visible, bbox.take(), subpath_start);
+            subpath_start = visible.len();
+        }
+        let points = match element {
+            PathEl::MoveTo(p0) | PathEl::LineTo(p0) => [Some(p0), None, None],
+            PathEl::QuadTo(p0, p1) => [Some(p0), Some(p1), None],
+            PathEl::CurveTo(p0, p1, p2) => [Some(p0), Some(p1), Some(p2)],
+            PathEl::ClosePath => [None; 3],
+        };
+        for point in points.into_iter().flatten() {
+            bbox = Some(bbox.map_or(Rect::from_points(point, point), |bbox| bbox.union_pt(point)));
+        }
+        visible.push(element);
+    }
+    end_subpath(visible, bbox, subpath_start);
+    culled
+}
@@ -0,0 +1,102 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use crate::{wide_tile::WIDE_TILE_WIDTH_PX, Command, Tile, WideTile};
+
+/// The wide tiles whose draw commands changed between frames, see
+/// [`Bintje::damage`](crate::Bintje::damage).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Damage {
+    /// The width of the render target in pixels.
+    width: u16,
+    /// The height of the render target in pixels.
+    height: u16,
+    /// Whether each wide tile is dirty, in row-major order.
+    dirty: Vec<bool>,
+}
+
+impl Damage {
+    pub(crate) fn new(width: u16, height: u16, dirty: Vec<bool>) -> Self {
+        Self {
+            width,
+            height,
+            dirty,
+        }
+    }
+
+    /// Whether each wide tile is dirty, in the row-major order of
+    /// [`Commands::wide_tiles`](crate::Commands::wide_tiles).
+    pub fn wide_tiles(&self) -> &[bool] {
+        &self.dirty
+    }
+
+    /// Whether no wide tile is dirty, i.e., the rendered frame is unchanged.
+    pub fn is_empty(&self) -> bool {
+        !self.dirty.contains(&true)
+    }
+
+    /// The bounding rectangle of the dirty wide tiles in pixels, clipped to the render target, or
+    /// `None` if no wide tile is dirty.
+    pub fn rect(&self) -> Option<kurbo::Rect> {
+        let wide_tile_columns = self.width.div_ceil(WIDE_TILE_WIDTH_PX) as usize;
+        let (mut x0, mut y0, mut x1, mut y1) = (usize::MAX, usize::MAX, 0, 0);
+        for (idx, _) in self.dirty.iter().enumerate().filter(|(_, dirty)| **dirty) {
+            let (x, y) = (idx % wide_tile_columns, idx / wide_tile_columns);
+            x0 = x0.min(x);
+            y0 = y0.min(y);
+            x1 = x1.max(x + 1);
+            y1 = y1.max(y + 1);
+        }
+        if x0 == usize::MAX {
+            return None;
+        }
+
+        let (wide_tile_width, wide_tile_height) =
+            (f64::from(WIDE_TILE_WIDTH_PX), f64::from(Tile::HEIGHT));
+        Some(kurbo::Rect::new(
+            x0 as f64 * wide_tile_width,
+            y0 as f64 * wide_tile_height,
+            (x1 as f64 * wide_tile_width).min(self.width.into()),
+            (y1 as f64 * wide_tile_height).min(self.height.into()),
+        ))
+    }
+}
+
+/// Hash the commands of a wide tile, including the alpha masks they sample from.
+///
+/// Draw IDs are not hashed, as they do not affect the rasterized pixels.
+pub(crate) fn wide_tile_hash(wide_tile: &WideTile, alpha_masks: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for command in &wide_tile.commands {
+        match command {
+            Command::Sample(sample) => {
+                let alpha_mask_len =
+                    sample.width as usize * Tile::WIDTH as usize * Tile::HEIGHT as usize;
+                (0_u8, sample.x, sample.width, sample.color.to_u32()).hash(&mut hasher);
+                alpha_masks[sample.alpha_idx as usize..sample.alpha_idx as usize + alpha_mask_len]
+                    .hash(&mut hasher);
+            }
+            Command::SparseSample(sparse_sample) => {
+                (
+                    1_u8,
+                    sparse_sample.x,
+                    sparse_sample.width,
+                    sparse_sample.color.to_u32(),
+                    sparse_sample.alpha_mask,
+                )
+                    .hash(&mut hasher);
+            }
+            Command::SparseFill(sparse_fill) => {
+                (
+                    2_u8,
+                    sparse_fill.x,
+                    sparse_fill.width,
+                    sparse_fill.color.to_u32(),
+                )
+                    .hash(&mut hasher);
+            }
+            Command::PushClip(()) => 3_u8.hash(&mut hasher),
+            Command::PopClip(()) => 4_u8.hash(&mut hasher),
+        }
+    }
+    hasher.finish()
+}
@@ -0,0 +1,210 @@
+use peniko::color::PremulRgba8;
+
+use crate::{
+    try_cpu_rasterize,
+    wide_tile::{mul_alpha, over, WIDE_TILE_WIDTH_PX},
+    Command, Commands, Error, FineRasterizer, RenderTarget, Tile,
+};
+
+/// Translucent tint of alpha-sampled areas.
+const SAMPLE_TINT: PremulRgba8 = premul(255, 140, 0, 96);
+/// Translucent tint of areas between strips sampling from an alpha mask column.
+const SPARSE_SAMPLE_TINT: PremulRgba8 = premul(0, 170, 0, 96);
+/// Translucent tint of opaque areas between strips.
+const SPARSE_FILL_TINT: PremulRgba8 = premul(0, 90, 255, 96);
+/// The outline of alpha-sampled areas.
+const SAMPLE_OUTLINE: PremulRgba8 = premul(160, 60, 0, 255);
+/// The wide tile boundaries.
+const WIDE_TILE_BOUNDARY: PremulRgba8 = premul(96, 96, 96, 255);
+/// The alpha of the command count heatmap.
+const HEATMAP_ALPHA: u8 = 80;
+
+/// A rasterizer visualizing how a scene is encoded into draw commands, to help understand the
+/// performance of specific scenes.
+///
+/// The scene is rasterized like [`CpuRasterizer`](crate::CpuRasterizer) and faded over white, after
+/// which the enabled overlays are drawn over it, in the order of the fields below. The resulting
+/// pixels are opaque. Damage is ignored, and the whole render target is drawn.
+#[derive(Clone, Copy, Debug)]
+pub struct DebugRasterizer {
+    /// Tint every wide tile by its number of commands, relative to the wide tile with the most
+    /// commands, from blue (fewest) to red (most).
+    pub heatmap: bool,
+    /// Tint the areas covered by commands by kind: alpha-sampled areas in orange, areas sampling
+    /// from an alpha mask column in green, and opaque fills in blue. Overlapping commands make the
+    /// tint stronger.
+    pub commands: bool,
+    /// Outline the area of every command sampling an alpha mask. Strips crossing wide tile
+    /// boundaries are outlined per wide tile, and fully covered parts of strips are drawn as fills,
+    /// which are not outlined.
+    pub samples: bool,
+    /// Draw the top and left boundaries of every wide tile.
+    pub wide_tiles: bool,
+}
+
+impl Default for DebugRasterizer {
+    fn default() -> Self {
+        Self {
+            heatmap: true,
+            commands: true,
+            samples: true,
+            wide_tiles: true,
+        }
+    }
+}
+
+impl FineRasterizer for DebugRasterizer {
+    fn rasterize(&mut self, commands: Commands<'_>, target: RenderTarget<'_>) -> Result<(), Error> {
+        span!(
+            "DebugRasterizer::rasterize",
+            width = target.width,
+            height = target.height
+        );
+        let RenderTarget {
+            width,
+            height,
+            pixels,
+            ..
+        } = target;
+        try_cpu_rasterize(
+            width,
+            height,
+            pixels,
+            commands.alpha_masks,
+            commands.wide_tiles,
+        )?;
+        let white = premul(255, 255, 255, 255);
+        for pixel in pixels.iter_mut() {
+            *pixel = over(white, mul_alpha(*pixel, 128));
+        }
+
+        let mut canvas = Canvas {
+            width,
+            height,
+            pixels,
+        };
+        let wide_tile_columns = width.div_ceil(WIDE_TILE_WIDTH_PX);
+        let max_commands = commands
+            .wide_tiles
+            .iter()
+            .map(|wide_tile| wide_tile.commands.len())
+            .max()
+            .unwrap_or(0);
+        let (tile_width, tile_height) = (u32::from(Tile::WIDTH), u32::from(Tile::HEIGHT));
+        for (wide_tile_idx, wide_tile) in commands.wide_tiles.iter().enumerate() {
+            let row = u32::try_from(wide_tile_idx / usize::from(wide_tile_columns))
+                .expect("wide tile rows fit in u16");
+            let column = u32::try_from(wide_tile_idx % usize::from(wide_tile_columns))
+                .expect("wide tile columns fit in u16");
+            let x0 = column * u32::from(WIDE_TILE_WIDTH_PX);
+            let y0 = row * tile_height;
+            let y1 = y0 + tile_height;
+
+            if self.heatmap && max_commands > 0 {
+                canvas.fill(
+                    x0,
+                    y0,
+                    x0 + u32::from(WIDE_TILE_WIDTH_PX),
+                    y1,
+                    heat(wide_tile.commands.len(), max_commands),
+                );
+            }
+
+            for command in &wide_tile.commands {
+                let (x, command_width, tint) = match command {
+                    Command::Sample(sample) => (sample.x, sample.width, SAMPLE_TINT),
+                    Command::SparseSample(sparse_sample) => {
+                        (sparse_sample.x, sparse_sample.width, SPARSE_SAMPLE_TINT)
+                    }
+                    Command::SparseFill(sparse_fill) => {
+                        (sparse_fill.x, sparse_fill.width, SPARSE_FILL_TINT)
+                    }
+                    Command::PushClip(()) | Command::PopClip(()) => continue,
+                };
+                let cmd_x0 = x0 + u32::from(x) * tile_width;
+                let cmd_x1 = cmd_x0 + u32::from(command_width) * tile_width;
+                if self.commands {
+                    canvas.fill(cmd_x0, y0, cmd_x1, y1, tint);
+                }
+                if self.samples && matches!(command, Command::Sample(_)) {
+                    canvas.outline(cmd_x0, y0, cmd_x1, y1, SAMPLE_OUTLINE);
+                }
+            }
+
+            if self.wide_tiles {
+                canvas.fill(
+                    x0,
+                    y0,
+                    x0 + u32::from(WIDE_TILE_WIDTH_PX),
+                    y0 + 1,
+                    WIDE_TILE_BOUNDARY,
+                );
+                canvas.fill(x0, y0, x0 + 1, y1, WIDE_TILE_BOUNDARY);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Row-major pixels to draw overlays on, clipped to the render target.
+struct Canvas<'a> {
+    width: u16,
+    height: u16,
+    pixels: &'a mut [PremulRgba8],
+}
+
+impl Canvas<'_> {
+    /// Composite `color` over the pixels `x0..x1` by `y0..y1`.
+    fn fill(&mut self, x0: u32, y0: u32, x1: u32, y1: u32, color: PremulRgba8) {
+        let x1 = x1.min(self.width.into());
+        let y1 = y1.min(self.height.into());
+        for y in y0..y1 {
+            let row = y as usize * usize::from(self.width);
+            for x in x0..x1 {
+                let pixel = &mut self.pixels[row + x as usize];
+                *pixel = over(*pixel, color);
+            }
+        }
+    }
+
+    /// Draw a 1px outline on the inside of the pixels `x0..x1` by `y0..y1`. Empty areas are not
+    /// outlined.
+    fn outline(&mut self, x0: u32, y0: u32, x1: u32, y1: u32, color: PremulRgba8) {
+        if x0 >= x1 || y0 >= y1 {
+            return;
+        }
+        self.fill(x0, y0, x1, y0 + 1, color);
+        self.fill(x0, y1 - 1, x1, y1, color);
+        self.fill(x0, y0 + 1, x0 + 1, y1 - 1, color);
+        self.fill(x1 - 1, y0 + 1, x1, y1 - 1, color);
+    }
+}
+
+/// The heatmap color of a wide tile with `count` commands, where the busiest wide tile has `max`
+/// commands.
+fn heat(count: usize, max: usize) -> PremulRgba8 {
+    #[expect(
+        clippy::cast_possible_truncation,
+        reason = "count is at most max, so the result is at most 255"
+    )]
+    let red = (count * 255 / max) as u8;
+    mul_alpha(premul(red, 0, 255 - red, 255), HEATMAP_ALPHA)
+}
+
+/// A premultiplied color from unpremultiplied components.
+const fn premul(r: u8, g: u8, b: u8, a: u8) -> PremulRgba8 {
+    const fn mul(c: u8, a: u8) -> u8 {
+        #[expect(
+            clippy::cast_possible_truncation,
+            reason = "the product divided by 255 is at most 255"
+        )]
+        let product = ((c as u16 * a as u16 + 127) / 255) as u8;
+        product
+    }
+    PremulRgba8 {
+        r: mul(r, a),
+        g: mul(g, a),
+        b: mul(b, a),
+        a,
+    }
+}
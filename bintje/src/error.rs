@@ -0,0 +1,94 @@
+use core::fmt;
+
+use crate::CommandError;
+
+/// An error returned by the fallible variants of Bintje's entry points.
+///
+/// The infallible variants panic with this error's message instead.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Error {
+    /// A flattening tolerance was given that is not positive and finite.
+    InvalidTolerance(f64),
+    /// A path flattened to more lines than can be indexed. The draw was skipped.
+    TooManyLines,
+    /// The font data could not be read, or contains malformed glyph outlines. The draw was
+    /// skipped.
+    #[cfg(feature = "glyphs")]
+    InvalidFont,
+    /// A path to prepare is too large, or too far from the origin. See
+    /// [`Bintje::prepare_path`](crate::Bintje::prepare_path).
+    PathTooLarge,
+    /// The image to rasterize to does not have one pixel per pixel of the render target.
+    ImageSizeMismatch {
+        /// The number of pixels of the render target.
+        expected: usize,
+        /// The number of pixels of the image.
+        actual: usize,
+    },
+    /// A coverage mask is too small for the render target, or its rows overlap. See
+    /// [`Bintje::fill_mask`](crate::Bintje::fill_mask).
+    MaskTooSmall {
+        /// The number of bytes between the starts of consecutive rows.
+        stride: usize,
+        /// The number of bytes of the mask.
+        len: usize,
+    },
+    /// A rasterizer does not support rendering to a render target of this size, see
+    /// [`FineRasterizer`](crate::FineRasterizer).
+    UnsupportedTargetSize {
+        /// The width of the render target in pixels.
+        width: u16,
+        /// The height of the render target in pixels.
+        height: u16,
+    },
+    /// The draw commands to rasterize are malformed, see
+    /// [`validate_commands`](crate::validate_commands).
+    InvalidCommands(CommandError),
+    /// The number of wide tiles to rasterize does not match the size of the render target.
+    WideTileCountMismatch {
+        /// The number of wide tiles covering the render target.
+        expected: usize,
+        /// The number of wide tiles given.
+        actual: usize,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidTolerance(tolerance) => {
+                write!(f, "tolerance must be positive and finite, got {tolerance}")
+            }
+            Self::TooManyLines => write!(f, "number of lines per path overflowed"),
+            #[cfg(feature = "glyphs")]
+            Self::InvalidFont => write!(f, "failed to read the font"),
+            Self::PathTooLarge => write!(f, "path is too large to prepare"),
+            Self::ImageSizeMismatch { expected, actual } => write!(
+                f,
+                "image has {actual} pixels, but the render target has {expected} pixels"
+            ),
+            Self::MaskTooSmall { stride, len } => write!(
+                f,
+                "mask of {len} bytes with a stride of {stride} bytes does not fit the render target"
+            ),
+            Self::UnsupportedTargetSize { width, height } => write!(
+                f,
+                "rasterizer does not support render targets of {width}x{height} pixels"
+            ),
+            Self::InvalidCommands(err) => write!(f, "draw commands are malformed: {err}"),
+            Self::WideTileCountMismatch { expected, actual } => write!(
+                f,
+                "{actual} wide tiles were given, but the render target has {expected} wide tiles"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::InvalidCommands(err) => Some(err),
+            _ => None,
+        }
+    }
+}
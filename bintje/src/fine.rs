@@ -0,0 +1,54 @@
+use peniko::color::PremulRgba8;
+
+use crate::{try_cpu_rasterize, try_cpu_rasterize_damaged, Commands, Damage, Error};
+
+/// A pixel buffer to rasterize draw commands to, see [`FineRasterizer`].
+#[derive(Debug)]
+pub struct RenderTarget<'a> {
+    /// The width of the render target in pixels.
+    pub width: u16,
+    /// The height of the render target in pixels.
+    pub height: u16,
+    /// The pixels in row-major order. There must be `width * height` pixels.
+    pub pixels: &'a mut [PremulRgba8],
+    /// If given, only the dirty wide tiles are rasterized, and the other pixels are left
+    /// untouched, see [`Bintje::damage`](crate::Bintje::damage).
+    pub damage: Option<&'a Damage>,
+}
+
+/// A backend rasterizing draw commands to pixels, such that backends can be switched generically.
+///
+/// This is implemented by [`CpuRasterizer`], and by the `bintje_wgpu` rasterizer.
+pub trait FineRasterizer {
+    /// Rasterize the draw commands to the render target.
+    ///
+    /// Returns an error if the number of pixels or wide tiles does not match the size of the render
+    /// target, or if the backend cannot rasterize to a render target of that size.
+    fn rasterize(&mut self, commands: Commands<'_>, target: RenderTarget<'_>) -> Result<(), Error>;
+}
+
+/// Rasterization on the CPU, see [`cpu_rasterize`](crate::cpu_rasterize).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CpuRasterizer;
+
+impl FineRasterizer for CpuRasterizer {
+    fn rasterize(&mut self, commands: Commands<'_>, target: RenderTarget<'_>) -> Result<(), Error> {
+        match target.damage {
+            Some(damage) => try_cpu_rasterize_damaged(
+                target.width,
+                target.height,
+                target.pixels,
+                commands.alpha_masks,
+                commands.wide_tiles,
+                damage,
+            ),
+            None => try_cpu_rasterize(
+                target.width,
+                target.height,
+                target.pixels,
+                commands.alpha_masks,
+                commands.wide_tiles,
+            ),
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use skrifa::{
+    instance::{LocationRef, Size},
+    outline::OutlinePen,
+    FontRef, GlyphId, MetadataProvider,
+};
+
+use crate::Error;
+
+/// A positioned glyph, see [`Bintje::draw_glyphs`](crate::Bintje::draw_glyphs).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Glyph {
+    /// The glyph ID within the font.
+    pub id: u32,
+    /// The x-coordinate of the glyph's origin.
+    pub x: f32,
+    /// The y-coordinate of the glyph's origin, i.e., of the baseline.
+    pub y: f32,
+}
+
+/// Append the outlines of the glyphs in `font` at `font_size` pixels per em to `path`.
+///
+/// For font collections, the first font is used. Glyphs that are not in the font are skipped.
+pub(crate) fn glyph_run_path(
+    font: &[u8],
+    font_size: f32,
+    glyphs: impl IntoIterator<Item = Glyph>,
+    path: &mut kurbo::BezPath,
+) -> Result<(), Error> {
+    let font = FontRef::from_index(font, 0).map_err(|_| Error::InvalidFont)?;
+    let outlines = font.outline_glyphs();
+    for glyph in glyphs {
+        let Some(outline) = outlines.get(GlyphId::new(glyph.id)) else {
+            continue;
+        };
+        let mut pen = GlyphPen {
+            path,
+            x: glyph.x.into(),
+            y: glyph.y.into(),
+        };
+        outline
+            .draw((Size::new(font_size), LocationRef::default()), &mut pen)
+            .map_err(|_| Error::InvalidFont)?;
+    }
+    Ok(())
+}
+
+/// Build a path from a glyph outline, placing the glyph's origin at (`x`, `y`).
+///
+/// Glyph outlines are y-up, whereas the path is y-down.
+struct GlyphPen<'p> {
+    path: &'p mut kurbo::BezPath,
+    x: f64,
+    y: f64,
+}
+
+impl GlyphPen<'_> {
+    fn point(&self, x: f32, y: f32) -> kurbo::Point {
+        kurbo::Point::new(self.x + f64::from(x), self.y - f64::from(y))
+    }
+}
+
+impl OutlinePen for GlyphPen<'_> {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.path.move_to(self.point(x, y));
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.path.line_to(self.point(x, y));
+    }
+
+    fn quad_to(&mut self, cx0: f32, cy0: f32, x: f32, y: f32) {
+        self.path.quad_to(self.point(cx0, cy0), self.point(x, y));
+    }
+
+    fn curve_to(&mut self, cx0: f32, cy0: f32, cx1: f32, cy1: f32, x: f32, y: f32) {
+        self.path
+            .curve_to(self.point(cx0, cy0), self.point(cx1, cy1), self.point(x, y));
+    }
+
+    fn close(&mut self) {
+        self.path.close_path();
+    }
+}
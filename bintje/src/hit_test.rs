@@ -0,0 +1,60 @@
+use crate::{wide_tile::WIDE_TILE_WIDTH_PX, Command, Tile, WideTile};
+
+/// A draw covering a pixel, see [`Bintje::hit_test`](crate::Bintje::hit_test).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Hit {
+    /// The caller-supplied ID of the draw, see
+    /// [`Bintje::set_draw_id`](crate::Bintje::set_draw_id).
+    pub draw_id: u32,
+    /// The coverage of the pixel by the draw, where 255 is full coverage.
+    pub coverage: u8,
+}
+
+/// Yield the draws covering the pixel at (`x`, `y`), topmost first.
+///
+/// The pixel must be within the viewport of `width` pixels wide.
+pub(crate) fn hit_test<'c>(
+    width: u16,
+    x: u16,
+    y: u16,
+    wide_tiles: &'c [WideTile],
+    alpha_masks: &'c [u8],
+) -> impl Iterator<Item = Hit> + 'c {
+    let wide_tile_columns = width.div_ceil(WIDE_TILE_WIDTH_PX);
+    let wide_tile_idx = (y / Tile::HEIGHT) as usize * wide_tile_columns as usize
+        + (x / WIDE_TILE_WIDTH_PX) as usize;
+    // The pixel's location within the wide tile, with `tile_x` in tiles.
+    let tile_x = x % WIDE_TILE_WIDTH_PX / Tile::WIDTH;
+    let px_x = x % Tile::WIDTH;
+    let px_y = y % Tile::HEIGHT;
+
+    let covers = move |x: u16, width: u16| x <= tile_x && tile_x < x + width;
+    wide_tiles[wide_tile_idx]
+        .commands
+        .iter()
+        .rev()
+        .filter_map(move |command| {
+            let (draw_id, coverage) = match command {
+                Command::Sample(sample) if covers(sample.x, sample.width) => {
+                    let alpha_idx = sample.alpha_idx as usize
+                        + ((tile_x - sample.x) * Tile::WIDTH + px_x) as usize
+                            * Tile::HEIGHT as usize
+                        + px_y as usize;
+                    (sample.draw_id, alpha_masks[alpha_idx])
+                }
+                Command::SparseSample(sparse_sample)
+                    if covers(sparse_sample.x, sparse_sample.width) =>
+                {
+                    (
+                        sparse_sample.draw_id,
+                        sparse_sample.alpha_mask[px_y as usize],
+                    )
+                }
+                Command::SparseFill(sparse_fill) if covers(sparse_fill.x, sparse_fill.width) => {
+                    (sparse_fill.draw_id, u8::MAX)
+                }
+                _ => return None,
+            };
+            (coverage > 0).then_some(Hit { draw_id, coverage })
+        })
+}
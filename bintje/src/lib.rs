@@ -3,10 +3,37 @@
 use kurbo::{flatten, Affine, PathEl};
 use peniko::BrushRef;
 
+/// Enter a `tracing` span at trace level until the end of the current scope, if the `tracing`
+/// feature is enabled. Otherwise, this expands to nothing, and the fields are not evaluated.
+macro_rules! span {
+    ($name:literal $(, $($fields:tt)*)?) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!($name $(, $($fields)*)?).entered();
+    };
+}
+
+mod band;
+mod compact;
+mod cull;
+mod damage;
+mod debug;
+mod error;
+mod fine;
+#[cfg(feature = "glyphs")]
+mod glyph;
+mod hit_test;
 mod line;
+mod mask;
+mod path_strip;
 mod point;
+mod prepared;
+mod rect;
+mod stats;
 mod strip;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 mod tile;
+mod validate;
 mod wide_tile;
 
 #[cfg(test)]
@@ -15,10 +42,25 @@ mod tests;
 pub(crate) use line::Line;
 pub(crate) use point::Point;
 pub(crate) use strip::Strip;
-pub(crate) use tile::TileRow;
+pub(crate) use tile::{TileRow, TileSorter, TileSpan};
 
+pub use band::{render_bands, write_bands};
+pub use damage::Damage;
+pub use debug::DebugRasterizer;
+pub use error::Error;
+pub use fine::{CpuRasterizer, FineRasterizer, RenderTarget};
+#[cfg(feature = "glyphs")]
+pub use glyph::Glyph;
+pub use hit_test::Hit;
+pub use path_strip::PathStrip;
+pub use prepared::PreparedPath;
+pub use stats::{CommandCounts, RenderStats};
 pub use tile::Tile;
-pub use wide_tile::{cpu_rasterize, Command, Sample, SparseFill, WideTile};
+pub use validate::{check_commands, validate_commands, CommandError};
+pub use wide_tile::{
+    cpu_rasterize, cpu_rasterize_damaged, try_cpu_rasterize, try_cpu_rasterize_damaged, Command,
+    Sample, SparseFill, WideTile,
+};
 
 /// The main render context.
 pub struct Bintje {
@@ -35,25 +77,47 @@ pub struct Bintje {
     current_transform: Affine,
     current_scale: f64,
 
+    /// The default flattening tolerance in pixels, see [`Bintje::set_tolerance`].
+    tolerance: f64,
+    /// The ID commands are tagged with, see [`Bintje::set_draw_id`].
+    draw_id: u32,
+
+    /// Reusable glyph run path scratch buffer.
+    #[cfg(feature = "glyphs")]
+    glyph_path: kurbo::BezPath,
+    /// Reusable scratch buffer for the subpaths of a path that are not culled.
+    visible_path: Vec<PathEl>,
+    /// Reusable scratch buffers for generating the strips of prepared paths.
+    prepare_scratch: prepared::PrepareScratch,
+
     /// The rendered wide tiles.
     ///
     /// These contain the draw commands, from which rasterization can proceed.
     wide_tiles: Vec<WideTile>,
     /// Alpha masks
     alpha_masks: Vec<u8>,
+    /// Reusable scratch buffer for compacting the alpha masks.
+    compact_scratch: Vec<u8>,
+    /// The alpha masks of the strips returned by [`Bintje::path_strips`].
+    path_alpha_masks: Vec<u8>,
 
     /// Reusable line scratch buffer.
     lines: Vec<Line>,
     /// Reusable tile scratch buffer.
     tile_rows: Vec<TileRow>,
+    /// Reusable tile span scratch buffer.
+    tile_spans: Vec<TileSpan>,
+    /// Reusable tile sorting scratch buffers.
+    tile_sorter: TileSorter,
     /// Reusable strip scratch buffer.
     strips: Vec<Strip>,
 
-    pub flattening_time: std::time::Duration,
-    pub flattening_stroke_time: std::time::Duration,
-    pub tile_generation_time: std::time::Duration,
-    pub tile_sorting_time: std::time::Duration,
-    pub strip_generation_time: std::time::Duration,
+    /// Diagnostics reported since the scene was last cleared.
+    diagnostics: Vec<Diagnostic>,
+    /// Statistics since the scene was last cleared.
+    stats: RenderStats,
+    /// The per-wide-tile command hashes of the frame [`Bintje::damage`] was last called for.
+    damage_hashes: Vec<u64>,
 }
 
 /// Draw commands.
@@ -62,11 +126,26 @@ pub struct Bintje {
 /// commands contain an index into the alpha mask buffer.
 ///
 /// TODO(Tom): the name is confusing, as wide tiles also contain commands.
+#[derive(Clone, Copy, Debug)]
 pub struct Commands<'c> {
     pub wide_tiles: &'c [WideTile],
     pub alpha_masks: &'c [u8],
 }
 
+/// A diagnostic reported while generating draw commands.
+///
+/// Invalid input does not cause a panic. Instead, the offending draw is skipped and a diagnostic
+/// is reported, see [`Bintje::diagnostics`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Diagnostic {
+    /// The geometry of a draw contained NaN or infinite coordinates, possibly only after the
+    /// current transform was applied. The draw was skipped.
+    NonFiniteGeometry,
+    /// A transform was pushed that is non-finite or singular. Draws are skipped until the
+    /// transform is popped.
+    DegenerateTransform,
+}
+
 struct Transform {
     transform: Affine,
     scale: f64,
@@ -79,6 +158,9 @@ pub(crate) struct ClipState {
 }
 
 impl Bintje {
+    /// The default flattening tolerance in pixels.
+    pub const DEFAULT_TOLERANCE: f64 = 0.25;
+
     /// Create a new renderer with the given pixel width and height.
     pub fn new(width: u16, height: u16) -> Self {
         let wide_tile_columns = width.div_ceil(wide_tile::WIDE_TILE_WIDTH_PX);
@@ -93,6 +175,7 @@ impl Bintje {
             }
         }
 
+        let stats = RenderStats::new(wide_tiles.len());
         Self {
             width,
             height,
@@ -100,17 +183,24 @@ impl Bintje {
             transform_stack: Vec::with_capacity(16),
             current_transform: Affine::IDENTITY,
             current_scale: 1.,
+            tolerance: Self::DEFAULT_TOLERANCE,
+            draw_id: 0,
             wide_tiles,
             alpha_masks: Vec::with_capacity(65536),
+            compact_scratch: Vec::new(),
+            path_alpha_masks: Vec::new(),
             lines: Vec::with_capacity(512),
             tile_rows: vec![TileRow::new(); wide_tile_rows as usize],
+            tile_spans: Vec::with_capacity(512),
+            tile_sorter: TileSorter::default(),
             strips: Vec::with_capacity(64),
-
-            flattening_time: std::time::Duration::ZERO,
-            flattening_stroke_time: std::time::Duration::ZERO,
-            tile_sorting_time: std::time::Duration::ZERO,
-            tile_generation_time: std::time::Duration::ZERO,
-            strip_generation_time: std::time::Duration::ZERO,
+            #[cfg(feature = "glyphs")]
+            glyph_path: kurbo::BezPath::new(),
+            visible_path: Vec::new(),
+            prepare_scratch: prepared::PrepareScratch::default(),
+            diagnostics: Vec::new(),
+            stats,
+            damage_hashes: Vec::new(),
         }
     }
 
@@ -121,14 +211,58 @@ impl Bintje {
         (self.width, self.height)
     }
 
-    fn flatten_path(&mut self, path: impl kurbo::Shape) {
+    /// Set the default flattening tolerance in pixels, used by [`Bintje::fill_shape`] and
+    /// [`Bintje::stroke`].
+    ///
+    /// This is the maximum distance between a curve and the lines approximating it. Coarser
+    /// tolerances are faster to render, finer tolerances are more accurate. The tolerance is
+    /// measured in pixels, i.e., after the current transform is applied. Defaults to
+    /// [`Bintje::DEFAULT_TOLERANCE`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tolerance` is not positive and finite. See [`Bintje::try_set_tolerance`] for a
+    /// fallible variant.
+    pub fn set_tolerance(&mut self, tolerance: f64) {
+        if let Err(err) = self.try_set_tolerance(tolerance) {
+            panic!("{err}");
+        }
+    }
+
+    /// Set the default flattening tolerance like [`Bintje::set_tolerance`], returning an error if
+    /// `tolerance` is not positive and finite.
+    pub fn try_set_tolerance(&mut self, tolerance: f64) -> Result<(), Error> {
+        check_tolerance(tolerance)?;
+        self.tolerance = tolerance;
+        Ok(())
+    }
+
+    /// The default flattening tolerance in pixels, see [`Bintje::set_tolerance`].
+    pub fn tolerance(&self) -> f64 {
+        self.tolerance
+    }
+
+    /// The flattening tolerance in the path's coordinate system, before the current transform is
+    /// applied.
+    fn path_tolerance(&self, tolerance: f64) -> Result<f64, Error> {
+        check_tolerance(tolerance)?;
+        Ok(tolerance / self.current_scale)
+    }
+
+    /// Flatten the path into lines, transforming it by the current transform.
+    ///
+    /// The `tolerance` is in the path's coordinate system. Flattening stops at the first
+    /// non-finite path element. Returns whether all path elements were finite.
+    fn flatten_path(&mut self, path: impl kurbo::Shape, tolerance: f64) -> bool {
         let mut closed = true;
         let mut start = kurbo::Point::ZERO;
         let mut prev = kurbo::Point::ZERO;
+        let mut non_finite = false;
+        span!("flatten");
         let start_time = std::time::Instant::now();
         flatten(
-            path.path_elements(0.25 / self.current_scale),
-            0.25 / self.current_scale,
+            finite_path_elements(path.path_elements(tolerance), &mut non_finite),
+            tolerance,
             |path_element| {
                 let path_element = self.current_transform * path_element;
                 match path_element {
@@ -162,44 +296,111 @@ impl Bintje {
             self.lines
                 .push(Line::from_kurbo(kurbo::Line::new(prev, start)));
         }
-        self.flattening_time += start_time.elapsed();
+        self.stats.flattening_time += start_time.elapsed();
+        !non_finite
+    }
+
+    /// Check whether the current transform can be drawn with. Draws under a non-finite or
+    /// singular transform are skipped.
+    fn transform_is_valid(&self) -> bool {
+        self.current_transform.is_finite() && self.current_transform.determinant() != 0.
+    }
+
+    /// Check whether the flattened lines are finite. Coordinates may overflow when converted to
+    /// `f32`, even if the path elements themselves were finite.
+    ///
+    /// If the geometry is not finite, the lines are cleared and a diagnostic is reported.
+    fn check_lines_finite(&mut self, path_finite: bool) -> bool {
+        if path_finite && self.lines.iter().all(Line::is_finite) {
+            true
+        } else {
+            self.lines.clear();
+            self.diagnostics.push(Diagnostic::NonFiniteGeometry);
+            false
+        }
+    }
+
+    /// Clear the line, tile and strip scratch buffers before processing the next path.
+    fn clear_scratch(&mut self) {
+        self.lines.clear();
+        for tile_row in self.tile_rows.iter_mut() {
+            tile_row.clear();
+        }
+        self.strips.clear();
     }
 
     /// Consume the lines, turning them into tiles.
-    fn tile(&mut self) {
-        let start = std::time::Instant::now();
-        tile::generate_tiles(&mut self.tile_rows, self.width, &self.lines);
-        self.tile_generation_time += start.elapsed();
-        let start = std::time::Instant::now();
-        for row in self.tile_rows.iter_mut() {
-            row.sort();
+    ///
+    /// If there are more lines than can be indexed by tiles, the lines are cleared and an error is
+    /// returned.
+    fn tile(&mut self) -> Result<(), Error> {
+        if u32::try_from(self.lines.len()).is_err() {
+            self.lines.clear();
+            return Err(Error::TooManyLines);
+        }
+        self.stats.lines += self.lines.len();
+        {
+            span!("tile", lines = self.lines.len());
+            let start = std::time::Instant::now();
+            tile::generate_tiles(
+                &mut self.tile_rows,
+                &mut self.tile_spans,
+                self.width,
+                &self.lines,
+            );
+            self.stats.tile_generation_time += start.elapsed();
         }
-        self.tile_sorting_time += start.elapsed();
+        {
+            span!(
+                "sort",
+                tiles = self
+                    .tile_rows
+                    .iter()
+                    .map(|row| row.tiles.len())
+                    .sum::<usize>()
+            );
+            let start = std::time::Instant::now();
+            for row in self.tile_rows.iter_mut() {
+                row.sort(&mut self.tile_sorter);
+                self.stats.tiles += row.tiles.len();
+            }
+            self.stats.tile_sorting_time += start.elapsed();
+        }
+        Ok(())
     }
 
     /// Consume tiles, turning them into strips.
     fn strip(&mut self) {
+        span!("strip", lines = self.lines.len());
         let start = std::time::Instant::now();
+        let alpha_masks_len = self.alpha_masks.len();
+        let width_in_tiles = self.width.div_ceil(Tile::WIDTH);
         for (y, row) in self.tile_rows.iter().enumerate() {
             strip::generate_strips(
                 row,
                 y as u16,
+                width_in_tiles,
                 &self.lines,
                 &mut self.alpha_masks,
                 &mut self.strips,
             );
         }
-        self.strip_generation_time += start.elapsed();
+        self.stats.strip_generation_time += start.elapsed();
+        self.stats.alpha_mask_bytes += self.alpha_masks.len() - alpha_masks_len;
     }
 
     /// Consume strips, turning them into wide tile commands.
     fn widen<'b>(&mut self, brush: impl Into<BrushRef<'b>>) {
+        span!("widen", strips = self.strips.len());
+        self.stats.strips += self.strips.len();
         wide_tile::generate_wide_tile_commands(
             self.width,
             &mut self.wide_tiles,
             &self.strips,
             &self.alpha_masks,
+            &mut self.stats.wide_tile_commands,
             brush,
+            self.draw_id,
         );
     }
 
@@ -208,9 +409,26 @@ impl Bintje {
         for wide_tile in self.wide_tiles.iter_mut() {
             wide_tile.commands.clear();
         }
+        self.alpha_masks.clear();
+        self.diagnostics.clear();
+        self.stats.reset();
         self.transform_stack.clear();
         self.current_transform = Affine::IDENTITY;
         self.current_scale = 1.;
+        self.draw_id = 0;
+    }
+
+    /// Set the ID to tag the commands of subsequent draws with.
+    ///
+    /// The ID is reported by [`Bintje::hit_test`], such that hits can be mapped back to the draws
+    /// that caused them. IDs do not need to be unique. The ID is 0 when the scene is cleared.
+    pub fn set_draw_id(&mut self, draw_id: u32) {
+        self.draw_id = draw_id;
+    }
+
+    /// The ID commands are tagged with, see [`Bintje::set_draw_id`].
+    pub fn draw_id(&self) -> u32 {
+        self.draw_id
     }
 
     /// Push an affine transform. Subsequent commands will have this transform applied.
@@ -223,10 +441,11 @@ impl Bintje {
         });
 
         self.current_transform *= transform;
-        self.current_scale = f64::max(
-            self.current_transform.as_coeffs()[0].abs(),
-            self.current_transform.as_coeffs()[3].abs(),
-        );
+        self.current_scale = max_scale(self.current_transform);
+
+        if !self.transform_is_valid() {
+            self.diagnostics.push(Diagnostic::DegenerateTransform);
+        }
     }
 
     /// Pop the last-pushed affine transform, returning to the transform before it.
@@ -240,68 +459,673 @@ impl Bintje {
     /// Fill a shape defined by `path` with the given `brush` (currently only solid colors are
     /// supported).
     ///
-    /// This generates wide tile draw commands.
+    /// This generates wide tile draw commands. If the shape contains non-finite coordinates, it is
+    /// skipped and [`Diagnostic::NonFiniteGeometry`] is reported.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the shape flattens to more lines than can be indexed. See
+    /// [`Bintje::try_fill_shape`] for a fallible variant.
     pub fn fill_shape<'b>(
         &mut self,
         path: impl kurbo::Shape,
         brush: impl Into<peniko::BrushRef<'b>>,
     ) {
-        self.lines.clear();
-        for tile_row in self.tile_rows.iter_mut() {
-            tile_row.clear();
+        if let Err(err) = self.try_fill_shape(path, brush) {
+            panic!("{err}");
+        }
+    }
+
+    /// Fill a shape like [`Bintje::fill_shape`], returning an error rather than panicking.
+    pub fn try_fill_shape<'b>(
+        &mut self,
+        path: impl kurbo::Shape,
+        brush: impl Into<peniko::BrushRef<'b>>,
+    ) -> Result<(), Error> {
+        self.try_fill_shape_with_tolerance(path, brush, self.tolerance)
+    }
+
+    /// Fill a shape like [`Bintje::fill_shape`], flattening with the given `tolerance` in pixels
+    /// rather than the default tolerance.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tolerance` is not positive and finite, or if the shape flattens to more lines
+    /// than can be indexed. See [`Bintje::try_fill_shape_with_tolerance`] for a fallible variant.
+    pub fn fill_shape_with_tolerance<'b>(
+        &mut self,
+        path: impl kurbo::Shape,
+        brush: impl Into<peniko::BrushRef<'b>>,
+        tolerance: f64,
+    ) {
+        if let Err(err) = self.try_fill_shape_with_tolerance(path, brush, tolerance) {
+            panic!("{err}");
+        }
+    }
+
+    /// Fill a shape like [`Bintje::fill_shape_with_tolerance`], returning an error rather than
+    /// panicking.
+    pub fn try_fill_shape_with_tolerance<'b>(
+        &mut self,
+        path: impl kurbo::Shape,
+        brush: impl Into<peniko::BrushRef<'b>>,
+        tolerance: f64,
+    ) -> Result<(), Error> {
+        span!("fill_shape");
+        let tolerance = self.path_tolerance(tolerance)?;
+        if !self.transform_is_valid() {
+            return Ok(());
+        }
+
+        if let Some(rect) = path.as_rect() {
+            if self.transform_is_axis_aligned() {
+                self.fill_axis_aligned_rect(rect, brush);
+                return Ok(());
+            }
+        }
+
+        if self.shape_strips(path, tolerance)? {
+            self.widen(brush);
+        }
+        Ok(())
+    }
+
+    /// Generate the strips of a shape, flattening it with the given `tolerance` in the path's
+    /// coordinate system. The current transform must be valid.
+    ///
+    /// Returns whether strips were generated, i.e., whether the shape was not culled and its
+    /// geometry was finite.
+    fn shape_strips(&mut self, path: impl kurbo::Shape, tolerance: f64) -> Result<bool, Error> {
+        // Skip the path if it is outside the viewport. If it is partially outside, skip the
+        // subpaths that are.
+        let bbox = self
+            .current_transform
+            .transform_rect_bbox(path.bounding_box());
+        self.clear_scratch();
+        let path_finite = match cull::visibility(bbox, self.width, self.height) {
+            cull::Visibility::Outside => {
+                self.stats.culled_draws += 1;
+                return Ok(false);
+            }
+            cull::Visibility::Inside => self.flatten_path(path, tolerance),
+            cull::Visibility::Partial => {
+                let mut visible = std::mem::take(&mut self.visible_path);
+                visible.clear();
+                let mut non_finite = false;
+                self.stats.culled_subpaths += cull::cull_subpaths(
+                    finite_path_elements(path.path_elements(tolerance), &mut non_finite),
+                    self.current_transform,
+                    0.,
+                    self.width,
+                    self.height,
+                    &mut visible,
+                );
+                let path_finite = self.flatten_path(visible.as_slice(), tolerance) && !non_finite;
+                self.visible_path = visible;
+                path_finite
+            }
+        };
+        if !self.check_lines_finite(path_finite) {
+            return Ok(false);
         }
+        self.tile()?;
+        self.strip();
+        Ok(true)
+    }
+
+    /// Fill a shape defined by `path` into an 8-bit coverage mask, rather than generating draw
+    /// commands.
+    ///
+    /// `mask` has the size of the render target, with rows `stride` bytes apart. The shape's
+    /// coverage is combined with the coverage already in the mask as a union, such that multiple
+    /// shapes can be accumulated into one mask. Bytes between rows are left untouched. The current
+    /// transform and the default flattening tolerance are applied. If the shape contains
+    /// non-finite coordinates, it is skipped and [`Diagnostic::NonFiniteGeometry`] is reported.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `mask` is too small for `stride`, if `stride` is smaller than the width of the
+    /// render target, or if the shape flattens to more lines than can be indexed. See
+    /// [`Bintje::try_fill_mask`] for a fallible variant.
+    pub fn fill_mask(&mut self, path: impl kurbo::Shape, mask: &mut [u8], stride: usize) {
+        if let Err(err) = self.try_fill_mask(path, mask, stride) {
+            panic!("{err}");
+        }
+    }
+
+    /// Fill a shape into a coverage mask like [`Bintje::fill_mask`], returning an error rather
+    /// than panicking.
+    pub fn try_fill_mask(
+        &mut self,
+        path: impl kurbo::Shape,
+        mask: &mut [u8],
+        stride: usize,
+    ) -> Result<(), Error> {
+        span!("fill_mask");
+        if mask::mask_len(self.width, self.height, stride).is_none_or(|len| mask.len() < len) {
+            return Err(Error::MaskTooSmall {
+                stride,
+                len: mask.len(),
+            });
+        }
+        if !self.transform_is_valid() {
+            return Ok(());
+        }
+
+        let tolerance = self.path_tolerance(self.tolerance)?;
+        let alpha_masks_len = self.alpha_masks.len();
+        if self.shape_strips(path, tolerance)? {
+            mask::fill_mask(
+                &self.strips,
+                &self.alpha_masks,
+                self.width,
+                self.height,
+                mask,
+                stride,
+            );
+        }
+        // No commands sample the strips' alpha masks.
+        self.alpha_masks.truncate(alpha_masks_len);
+        Ok(())
+    }
+
+    /// Get the strips of a shape defined by `path`, rather than generating draw commands.
+    ///
+    /// This is for consuming Bintje's coarse rasterization in custom backends: the strips describe
+    /// the shape's coverage in pixel coordinates, independent of wide tiles and brushes. Strips are
+    /// ordered by row, and from left to right within a row. The current transform and the default
+    /// flattening tolerance are applied, and the strips are clipped to the viewport's rows. If the
+    /// shape contains non-finite coordinates, there are no strips and
+    /// [`Diagnostic::NonFiniteGeometry`] is reported.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the shape flattens to more lines than can be indexed. See
+    /// [`Bintje::try_path_strips`] for a fallible variant.
+    pub fn path_strips(&mut self, path: impl kurbo::Shape) -> impl Iterator<Item = PathStrip<'_>> {
+        match self.try_path_strips(path) {
+            Ok(strips) => strips,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// Get the strips of a shape like [`Bintje::path_strips`], returning an error rather than
+    /// panicking.
+    pub fn try_path_strips(
+        &mut self,
+        path: impl kurbo::Shape,
+    ) -> Result<impl Iterator<Item = PathStrip<'_>>, Error> {
+        span!("path_strips");
+        let tolerance = self.path_tolerance(self.tolerance)?;
         self.strips.clear();
-        self.flatten_path(path);
-        self.tile();
+        if self.transform_is_valid() {
+            // Generate the alpha masks into a separate buffer, as no commands sample them.
+            std::mem::swap(&mut self.alpha_masks, &mut self.path_alpha_masks);
+            self.alpha_masks.clear();
+            let result = self.shape_strips(path, tolerance);
+            std::mem::swap(&mut self.alpha_masks, &mut self.path_alpha_masks);
+            if !result? {
+                self.strips.clear();
+            }
+        }
+        Ok(path_strip::path_strips(
+            &self.strips,
+            &self.path_alpha_masks,
+        ))
+    }
+
+    /// Fill a rectangle with the given `brush` (currently only solid colors are supported).
+    ///
+    /// If the current transform keeps the rectangle axis-aligned, this takes a fast path: the
+    /// rectangle's edge coverage is computed analytically, and its interior is filled directly,
+    /// skipping flattening, tile generation and strip generation. Otherwise this is equivalent to
+    /// [`Bintje::fill_shape`].
+    ///
+    /// This generates wide tile draw commands. If the rectangle contains non-finite coordinates, it
+    /// is skipped and [`Diagnostic::NonFiniteGeometry`] is reported.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the transformed rectangle is not axis-aligned and flattens to more lines than can
+    /// be indexed. See [`Bintje::try_fill_rect`] for a fallible variant.
+    pub fn fill_rect<'b>(&mut self, rect: kurbo::Rect, brush: impl Into<peniko::BrushRef<'b>>) {
+        if let Err(err) = self.try_fill_rect(rect, brush) {
+            panic!("{err}");
+        }
+    }
+
+    /// Fill a rectangle like [`Bintje::fill_rect`], returning an error rather than panicking.
+    pub fn try_fill_rect<'b>(
+        &mut self,
+        rect: kurbo::Rect,
+        brush: impl Into<peniko::BrushRef<'b>>,
+    ) -> Result<(), Error> {
+        span!("fill_rect");
+        if !self.transform_is_valid() {
+            return Ok(());
+        }
+        if !self.transform_is_axis_aligned() {
+            return self.try_fill_shape_with_tolerance(rect, brush, self.tolerance);
+        }
+        self.fill_axis_aligned_rect(rect, brush);
+        Ok(())
+    }
+
+    /// Fill a rectangle that the current transform keeps axis-aligned, computing its coverage
+    /// analytically.
+    fn fill_axis_aligned_rect<'b>(
+        &mut self,
+        rect: kurbo::Rect,
+        brush: impl Into<peniko::BrushRef<'b>>,
+    ) {
+        let rect = self.current_transform.transform_rect_bbox(rect);
+        if !rect.is_finite() {
+            self.diagnostics.push(Diagnostic::NonFiniteGeometry);
+            return;
+        }
+
+        self.clear_scratch();
+        let viewport = kurbo::Rect::new(0., 0., self.width.into(), self.height.into());
+        let alpha_masks_len = self.alpha_masks.len();
+        rect::generate_rect_strips(
+            rect.intersect(viewport),
+            &mut self.alpha_masks,
+            &mut self.strips,
+        );
+        self.stats.alpha_mask_bytes += self.alpha_masks.len() - alpha_masks_len;
+        self.widen(brush);
+    }
+
+    /// Whether the current transform maps axis-aligned rectangles to axis-aligned rectangles.
+    fn transform_is_axis_aligned(&self) -> bool {
+        let [a, b, c, d, _, _] = self.current_transform.as_coeffs();
+        (b == 0. && c == 0.) || (a == 0. && d == 0.)
+    }
+
+    /// Fill a closed polygon with the given `brush` (currently only solid colors are supported).
+    ///
+    /// The polygon is given by its vertices, and is implicitly closed. As the polygon is already
+    /// flat, this skips flattening. This is useful for callers that already have polylines, such
+    /// as map data or pre-tessellated font outlines.
+    ///
+    /// This generates wide tile draw commands. If the polygon contains non-finite coordinates, it
+    /// is skipped and [`Diagnostic::NonFiniteGeometry`] is reported.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the polygon has more edges than can be indexed. See
+    /// [`Bintje::try_fill_polygon`] for a fallible variant.
+    pub fn fill_polygon<'b>(
+        &mut self,
+        points: &[kurbo::Point],
+        brush: impl Into<peniko::BrushRef<'b>>,
+    ) {
+        if let Err(err) = self.try_fill_polygon(points, brush) {
+            panic!("{err}");
+        }
+    }
+
+    /// Fill a closed polygon like [`Bintje::fill_polygon`], returning an error rather than
+    /// panicking.
+    pub fn try_fill_polygon<'b>(
+        &mut self,
+        points: &[kurbo::Point],
+        brush: impl Into<peniko::BrushRef<'b>>,
+    ) -> Result<(), Error> {
+        span!("fill_polygon", points = points.len());
+        if !self.transform_is_valid() {
+            return Ok(());
+        }
+
+        self.clear_scratch();
+        if let (Some(&first), Some(&last)) = (points.first(), points.last()) {
+            for segment in points.windows(2) {
+                self.lines.push(Line::from_kurbo(
+                    self.current_transform * kurbo::Line::new(segment[0], segment[1]),
+                ));
+            }
+            if first != last {
+                self.lines.push(Line::from_kurbo(
+                    self.current_transform * kurbo::Line::new(last, first),
+                ));
+            }
+        }
+        if !self.check_lines_finite(true) {
+            return Ok(());
+        }
+        self.tile()?;
+        self.strip();
+        self.widen(brush);
+        Ok(())
+    }
+
+    /// Fill the area enclosed by `lines` with the given `brush` (currently only solid colors are
+    /// supported).
+    ///
+    /// The lines must form closed contours, though they may be given in any order. As the lines
+    /// are already flat, this skips flattening.
+    ///
+    /// This generates wide tile draw commands. If the lines contain non-finite coordinates, they
+    /// are skipped and [`Diagnostic::NonFiniteGeometry`] is reported.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are more lines than can be indexed. See [`Bintje::try_fill_lines`] for a
+    /// fallible variant.
+    pub fn fill_lines<'b>(
+        &mut self,
+        lines: &[kurbo::Line],
+        brush: impl Into<peniko::BrushRef<'b>>,
+    ) {
+        if let Err(err) = self.try_fill_lines(lines, brush) {
+            panic!("{err}");
+        }
+    }
+
+    /// Fill the area enclosed by `lines` like [`Bintje::fill_lines`], returning an error rather
+    /// than panicking.
+    pub fn try_fill_lines<'b>(
+        &mut self,
+        lines: &[kurbo::Line],
+        brush: impl Into<peniko::BrushRef<'b>>,
+    ) -> Result<(), Error> {
+        span!("fill_lines", lines = lines.len());
+        if !self.transform_is_valid() {
+            return Ok(());
+        }
+
+        self.clear_scratch();
+        self.lines.extend(
+            lines
+                .iter()
+                .map(|line| Line::from_kurbo(self.current_transform * *line)),
+        );
+        if !self.check_lines_finite(true) {
+            return Ok(());
+        }
+        self.tile()?;
         self.strip();
         self.widen(brush);
+        Ok(())
     }
 
     /// Stroke a shape defined by `path` with the given stroke style and `brush` (currently only
     /// solid colors are supported).
     ///
-    /// This generates wide tile draw commands.
+    /// This generates wide tile draw commands. If the shape or stroke width contains non-finite
+    /// values, it is skipped and [`Diagnostic::NonFiniteGeometry`] is reported.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the stroke expands to more lines than can be indexed. See
+    /// [`Bintje::try_stroke`] for a fallible variant.
     pub fn stroke<'b>(
         &mut self,
         path: impl IntoIterator<Item = PathEl>,
         style: &kurbo::Stroke,
         brush: impl Into<peniko::BrushRef<'b>>,
     ) {
+        if let Err(err) = self.try_stroke(path, style, brush) {
+            panic!("{err}");
+        }
+    }
+
+    /// Stroke a shape like [`Bintje::stroke`], returning an error rather than panicking.
+    pub fn try_stroke<'b>(
+        &mut self,
+        path: impl IntoIterator<Item = PathEl>,
+        style: &kurbo::Stroke,
+        brush: impl Into<peniko::BrushRef<'b>>,
+    ) -> Result<(), Error> {
+        self.try_stroke_with_tolerance(path, style, brush, self.tolerance)
+    }
+
+    /// Stroke a shape like [`Bintje::stroke`], flattening with the given `tolerance` in pixels
+    /// rather than the default tolerance.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tolerance` is not positive and finite, or if the stroke expands to more lines
+    /// than can be indexed. See [`Bintje::try_stroke_with_tolerance`] for a fallible variant.
+    pub fn stroke_with_tolerance<'b>(
+        &mut self,
+        path: impl IntoIterator<Item = PathEl>,
+        style: &kurbo::Stroke,
+        brush: impl Into<peniko::BrushRef<'b>>,
+        tolerance: f64,
+    ) {
+        if let Err(err) = self.try_stroke_with_tolerance(path, style, brush, tolerance) {
+            panic!("{err}");
+        }
+    }
+
+    /// Stroke a shape like [`Bintje::stroke_with_tolerance`], returning an error rather than
+    /// panicking.
+    pub fn try_stroke_with_tolerance<'b>(
+        &mut self,
+        path: impl IntoIterator<Item = PathEl>,
+        style: &kurbo::Stroke,
+        brush: impl Into<peniko::BrushRef<'b>>,
+        tolerance: f64,
+    ) -> Result<(), Error> {
+        span!("stroke");
+
+        check_tolerance(tolerance)?;
+        if !self.transform_is_valid() {
+            return Ok(());
+        }
+        if !style.width.is_finite() {
+            self.diagnostics.push(Diagnostic::NonFiniteGeometry);
+            return Ok(());
+        }
+
+        // Skip the subpaths whose stroke is outside the viewport, and skip the stroke entirely if
+        // all of them are.
+        let mut visible = std::mem::take(&mut self.visible_path);
+        visible.clear();
+        let mut non_finite = false;
+        let culled = cull::cull_subpaths(
+            finite_path_elements(path, &mut non_finite),
+            self.current_transform,
+            cull::stroke_inflation(style),
+            self.width,
+            self.height,
+            &mut visible,
+        );
+        let result = if visible.is_empty() && culled > 0 && !non_finite {
+            self.stats.culled_draws += 1;
+            Ok(())
+        } else {
+            self.stats.culled_subpaths += culled;
+            self.stroke_visible(&visible, non_finite, style, brush, tolerance)
+        };
+        self.visible_path = visible;
+        result
+    }
+
+    /// Stroke the path elements that were not culled, see [`Bintje::try_stroke_with_tolerance`].
+    ///
+    /// `non_finite` is whether the elements were cut short at a non-finite element.
+    fn stroke_visible<'b>(
+        &mut self,
+        path: &[PathEl],
+        non_finite: bool,
+        style: &kurbo::Stroke,
+        brush: impl Into<peniko::BrushRef<'b>>,
+        tolerance: f64,
+    ) -> Result<(), Error> {
         // Whether to use Kurbo's stroke expansion, or the experimental GPU stroke expansion
         // paper's expansion.
         const KURBO_STROKE_EXPANSION: bool = false;
 
+        let path_tolerance = self.path_tolerance(tolerance)?;
         if KURBO_STROKE_EXPANSION {
-            self.fill_shape(
-                kurbo::stroke(
-                    path,
-                    style,
-                    &kurbo::StrokeOpts::default(),
-                    0.25 / self.current_scale,
-                ),
-                brush,
+            if non_finite {
+                self.diagnostics.push(Diagnostic::NonFiniteGeometry);
+                return Ok(());
+            }
+            let stroke = kurbo::stroke(
+                path.iter().copied(),
+                style,
+                &kurbo::StrokeOpts::default(),
+                path_tolerance,
             );
+            self.try_fill_shape_with_tolerance(stroke, brush, tolerance)
         } else {
-            self.lines.clear();
-            for tile_row in self.tile_rows.iter_mut() {
-                tile_row.clear();
-            }
-            self.strips.clear();
+            self.clear_scratch();
             let start = std::time::Instant::now();
-            let lines: flatten::stroke::LoweredPath<kurbo::Line> =
-                flatten::stroke::stroke_undashed(path, style, 0.25 / self.current_scale);
+            {
+                span!("stroke_expansion");
+                let lines: flatten::stroke::LoweredPath<kurbo::Line> =
+                    flatten::stroke::stroke_undashed(path.iter().copied(), style, path_tolerance);
 
-            for line in lines.path.into_iter() {
-                self.lines
-                    .push(Line::from_kurbo(self.current_transform * line));
+                for line in lines.path.into_iter() {
+                    self.lines
+                        .push(Line::from_kurbo(self.current_transform * line));
+                }
             }
-            self.flattening_stroke_time += start.elapsed();
-            self.tile();
+            self.stats.flattening_stroke_time += start.elapsed();
+            if !self.check_lines_finite(!non_finite) {
+                return Ok(());
+            }
+            self.tile()?;
             self.strip();
             self.widen(brush);
+            Ok(())
         }
     }
 
+    /// Draw a run of glyphs with the given `brush` (currently only solid colors are supported).
+    ///
+    /// `font` is the raw data of a TrueType or OpenType font. For font collections, the first font
+    /// is used. The glyphs are drawn at `font_size` pixels per em, before the current transform is
+    /// applied, with their origins at the given positions. Glyphs that are not in the font are
+    /// skipped. The glyph outlines are filled as a single shape, like [`Bintje::fill_shape`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the font cannot be read. See [`Bintje::try_draw_glyphs`] for a fallible variant.
+    ///
+    /// This requires the `glyphs` feature.
+    #[cfg(feature = "glyphs")]
+    pub fn draw_glyphs<'b>(
+        &mut self,
+        font: &[u8],
+        font_size: f32,
+        glyphs: impl IntoIterator<Item = Glyph>,
+        brush: impl Into<peniko::BrushRef<'b>>,
+    ) {
+        if let Err(err) = self.try_draw_glyphs(font, font_size, glyphs, brush) {
+            panic!("{err}");
+        }
+    }
+
+    /// Draw a run of glyphs like [`Bintje::draw_glyphs`], returning an error rather than
+    /// panicking.
+    ///
+    /// This requires the `glyphs` feature.
+    #[cfg(feature = "glyphs")]
+    pub fn try_draw_glyphs<'b>(
+        &mut self,
+        font: &[u8],
+        font_size: f32,
+        glyphs: impl IntoIterator<Item = Glyph>,
+        brush: impl Into<peniko::BrushRef<'b>>,
+    ) -> Result<(), Error> {
+        span!("draw_glyphs");
+        let mut path = std::mem::take(&mut self.glyph_path);
+        path.truncate(0);
+        let result = glyph::glyph_run_path(font, font_size, glyphs, &mut path)
+            .and_then(|()| self.try_fill_shape(&path, brush));
+        self.glyph_path = path;
+        result
+    }
+
+    /// Prepare a shape defined by `path` for filling it repeatedly with
+    /// [`Bintje::fill_prepared_path`].
+    ///
+    /// The path is flattened with the current transform applied. If the shape contains non-finite
+    /// coordinates, or the current transform is non-finite or singular, the prepared path is
+    /// empty, and in the former case [`Diagnostic::NonFiniteGeometry`] is reported.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the shape flattens to more lines than can be indexed, or if it is too large or
+    /// too far from the origin. See [`Bintje::try_prepare_path`] for a fallible variant.
+    pub fn prepare_path(&mut self, path: impl kurbo::Shape) -> PreparedPath {
+        match self.try_prepare_path(path) {
+            Ok(prepared_path) => prepared_path,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// Prepare a shape like [`Bintje::prepare_path`], returning an error rather than panicking.
+    pub fn try_prepare_path(&mut self, path: impl kurbo::Shape) -> Result<PreparedPath, Error> {
+        span!("prepare_path");
+        let tolerance = self.path_tolerance(self.tolerance)?;
+        if !self.transform_is_valid() {
+            return Ok(PreparedPath::empty());
+        }
+
+        self.clear_scratch();
+        let path_finite = self.flatten_path(path, tolerance);
+        if !self.check_lines_finite(path_finite) {
+            return Ok(PreparedPath::empty());
+        }
+        if u32::try_from(self.lines.len()).is_err() {
+            self.lines.clear();
+            return Err(Error::TooManyLines);
+        }
+        let prepared = PreparedPath::new(&self.lines);
+        self.lines.clear();
+        prepared
+    }
+
+    /// Fill a prepared path, translated by `offset` in pixels, with the given `brush` (currently
+    /// only solid colors are supported).
+    ///
+    /// The current transform is not applied, as the path was transformed when it was prepared.
+    /// This skips flattening, and skips tile and strip generation if the path was filled before at
+    /// an offset with the same position within a tile, see [`PreparedPath`].
+    pub fn fill_prepared_path<'b>(
+        &mut self,
+        path: &mut PreparedPath,
+        offset: (i32, i32),
+        brush: impl Into<peniko::BrushRef<'b>>,
+    ) {
+        span!("fill_prepared_path");
+        self.clear_scratch();
+        let alpha_masks_len = self.alpha_masks.len();
+        prepared::generate_prepared_strips(
+            path,
+            offset,
+            self.width,
+            self.height,
+            &mut self.prepare_scratch,
+            &mut self.alpha_masks,
+            &mut self.strips,
+        );
+        self.stats.alpha_mask_bytes += self.alpha_masks.len() - alpha_masks_len;
+        self.widen(brush);
+    }
+
+    /// Deduplicate the alpha masks sampled by the draw commands, and drop the alpha masks that are
+    /// not sampled.
+    ///
+    /// Strips store an alpha mask for every tile they cover, but tiles that end up fully opaque
+    /// or fully transparent are not sampled, and edges often repeat the same coverage. Call this
+    /// after drawing and before rasterizing to reduce the memory used by, and the bandwidth needed
+    /// to upload, the alpha masks of [`Bintje::commands`]. The commands' alpha mask indices are
+    /// updated accordingly.
+    pub fn compact_alpha_masks(&mut self) {
+        span!("compact_alpha_masks");
+        compact::compact_alpha_masks(
+            &mut self.wide_tiles,
+            &mut self.alpha_masks,
+            &mut self.compact_scratch,
+        );
+    }
+
     /// Get the generated draw commands.
     pub fn commands(&self) -> Commands<'_> {
         Commands {
@@ -309,4 +1133,114 @@ impl Bintje {
             alpha_masks: &self.alpha_masks,
         }
     }
+
+    /// Get the draws covering the pixel at `point`, topmost first.
+    ///
+    /// `point` is in pixel coordinates, i.e., the current transform is not applied. The pixel at
+    /// (`x`, `y`) spans from `x` to `x + 1` and from `y` to `y + 1`. Each hit reports the ID the
+    /// draw was tagged with (see [`Bintje::set_draw_id`]) and its coverage of the pixel. Draws
+    /// that do not cover the pixel are not reported. If the point is outside the viewport, there
+    /// are no hits.
+    pub fn hit_test(&self, point: kurbo::Point) -> impl Iterator<Item = Hit> + '_ {
+        let inside = point.x >= 0.
+            && point.y >= 0.
+            && point.x < self.width.into()
+            && point.y < self.height.into();
+        #[expect(
+            clippy::cast_possible_truncation,
+            reason = "the point is within the viewport"
+        )]
+        let (x, y) = (point.x as u16, point.y as u16);
+        inside
+            .then(|| hit_test::hit_test(self.width, x, y, &self.wide_tiles, &self.alpha_masks))
+            .into_iter()
+            .flatten()
+    }
+
+    /// Get the diagnostics reported since the scene was last cleared.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Get the statistics of the work done since the scene was last cleared, or since the
+    /// statistics were last reset.
+    pub fn stats(&self) -> &RenderStats {
+        &self.stats
+    }
+
+    /// Reset the statistics to zero.
+    ///
+    /// Statistics are also reset when the scene is cleared.
+    pub fn reset_stats(&mut self) {
+        self.stats.reset();
+    }
+
+    /// Get the wide tiles whose draw commands changed since the last call to this method.
+    ///
+    /// The commands of every wide tile, including the alpha masks they sample from, are hashed
+    /// and compared against the hashes of the frame this method was last called for. Call this
+    /// once per frame after drawing, and rasterize only the dirty wide tiles into a persistent
+    /// target using [`cpu_rasterize_damaged`]. On the first call, or after
+    /// [`Bintje::reset_damage`], all wide tiles are dirty.
+    pub fn damage(&mut self) -> Damage {
+        span!("damage");
+        let first_frame = self.damage_hashes.is_empty();
+        self.damage_hashes.resize(self.wide_tiles.len(), 0);
+        let dirty = self
+            .wide_tiles
+            .iter()
+            .zip(&mut self.damage_hashes)
+            .map(|(wide_tile, prev_hash)| {
+                let hash = damage::wide_tile_hash(wide_tile, &self.alpha_masks);
+                let dirty = first_frame || hash != *prev_hash;
+                *prev_hash = hash;
+                dirty
+            })
+            .collect();
+        Damage::new(self.width, self.height, dirty)
+    }
+
+    /// Forget the previous frame, such that all wide tiles are dirty on the next call to
+    /// [`Bintje::damage`].
+    ///
+    /// Call this when the contents of the persistent render target are lost.
+    pub fn reset_damage(&mut self) {
+        self.damage_hashes.clear();
+    }
+}
+
+/// Check that the flattening tolerance is positive and finite.
+fn check_tolerance(tolerance: f64) -> Result<(), Error> {
+    if tolerance > 0. && tolerance.is_finite() {
+        Ok(())
+    } else {
+        Err(Error::InvalidTolerance(tolerance))
+    }
+}
+
+/// The maximum scale factor of the transform, i.e., the largest distance a unit vector can be
+/// mapped to. This is the transform's largest singular value.
+///
+/// Flattening tolerances are divided by this, such that the tolerance holds in every direction
+/// after the transform is applied, including for rotations and skews.
+fn max_scale(transform: Affine) -> f64 {
+    let [a, b, c, d, _, _] = transform.as_coeffs();
+    let s1 = a * a + b * b + c * c + d * d;
+    let s2 = ((a * a + b * b - c * c - d * d).powi(2) + 4. * (a * c + b * d).powi(2)).sqrt();
+    (0.5 * (s1 + s2)).sqrt()
+}
+
+/// Yield the path elements up to the first non-finite element. If a non-finite element is
+/// encountered, `non_finite` is set.
+///
+/// This prevents non-finite geometry from reaching flattening and stroke expansion.
+fn finite_path_elements<'a>(
+    path: impl IntoIterator<Item = PathEl> + 'a,
+    non_finite: &'a mut bool,
+) -> impl Iterator<Item = PathEl> + 'a {
+    path.into_iter().take_while(move |element| {
+        let finite = element.is_finite();
+        *non_finite |= !finite;
+        finite
+    })
 }
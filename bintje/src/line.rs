@@ -14,8 +14,13 @@ impl Line {
         }
     }
 
+    /// Whether both points of the line are finite.
+    pub(crate) fn is_finite(&self) -> bool {
+        self.p0.is_finite() && self.p1.is_finite()
+    }
+
     /// Rotate the line 90 degrees clockwise in a y-down coordinate system around the origin.
-    #[expect(unused, reason="may become useful again")]
+    #[expect(unused, reason = "may become useful again")]
     pub(crate) const fn turn_90(self) -> Line {
         Line {
             p0: self.p0.turn_90(),
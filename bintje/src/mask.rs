@@ -0,0 +1,99 @@
+use crate::{Strip, Tile};
+
+/// Accumulate the coverage of `strips` into `mask`, an 8-bit coverage mask of `width` by `height`
+/// pixels with rows `stride` bytes apart.
+///
+/// Coverage is combined with the coverage already in the mask as a union, i.e., like compositing
+/// alpha over alpha. The mask must be large enough, see [`mask_len`].
+pub(crate) fn fill_mask(
+    strips: &[Strip],
+    alpha_masks: &[u8],
+    width: u16,
+    height: u16,
+    mask: &mut [u8],
+    stride: usize,
+) {
+    let height_in_tiles = height.div_ceil(Tile::HEIGHT);
+    // Write the per-row `coverage` of pixels `x0..x1` in the tile row `tile_y`.
+    let fill_span = |mask: &mut [u8],
+                     tile_y: u16,
+                     x0: usize,
+                     x1: usize,
+                     coverage: [u8; Tile::HEIGHT as usize]| {
+        let x1 = x1.min(width.into());
+        for y in 0..Tile::HEIGHT {
+            let img_y = tile_y * Tile::HEIGHT + y;
+            if img_y >= height || x0 >= x1 {
+                break;
+            }
+            let row = img_y as usize * stride;
+            for pixel in &mut mask[row + x0..row + x1] {
+                *pixel = union(*pixel, coverage[y as usize]);
+            }
+        }
+    };
+
+    let mut prev_x = 0;
+    let mut prev_y = 0;
+    for strip in strips {
+        if strip.y >= height_in_tiles {
+            break;
+        }
+        // Areas between strips start at the viewport's left edge for the first strip of every
+        // row.
+        if strip.y != prev_y {
+            prev_x = 0;
+            prev_y = strip.y;
+        }
+
+        if strip.pixel_coverage != [0; Tile::HEIGHT as usize] && prev_x < strip.x {
+            fill_span(
+                mask,
+                strip.y,
+                usize::from(prev_x) * usize::from(Tile::WIDTH),
+                usize::from(strip.x) * usize::from(Tile::WIDTH),
+                strip.pixel_coverage,
+            );
+        }
+
+        let x0 = usize::from(strip.x) * usize::from(Tile::WIDTH);
+        let x1 = (x0 + usize::from(strip.width) * usize::from(Tile::WIDTH)).min(width.into());
+        for y in 0..Tile::HEIGHT {
+            let img_y = strip.y * Tile::HEIGHT + y;
+            if img_y >= height {
+                break;
+            }
+            let row = img_y as usize * stride;
+            for x in x0..x1 {
+                let alpha_idx =
+                    strip.alpha_idx as usize + (x - x0) * Tile::HEIGHT as usize + y as usize;
+                mask[row + x] = union(mask[row + x], alpha_masks[alpha_idx]);
+            }
+        }
+
+        prev_x = strip.x + strip.width;
+    }
+}
+
+/// The number of bytes a mask of `width` by `height` pixels with rows `stride` bytes apart needs,
+/// or `None` if the rows overlap.
+pub(crate) fn mask_len(width: u16, height: u16, stride: usize) -> Option<usize> {
+    if stride < width.into() {
+        None
+    } else if height == 0 {
+        Some(0)
+    } else {
+        Some(stride * (height as usize - 1) + width as usize)
+    }
+}
+
+/// The union of two coverages.
+fn union(under: u8, over: u8) -> u8 {
+    let uncovered = u16::from(u8::MAX - under);
+    #[expect(
+        clippy::cast_possible_truncation,
+        reason = "the added coverage is at most the uncovered coverage"
+    )]
+    let added = ((uncovered * u16::from(over) + 127) / 255) as u8;
+    under + added
+}
@@ -0,0 +1,54 @@
+use crate::{Strip, Tile};
+
+/// A strip of a path's coverage in pixel coordinates, see
+/// [`Bintje::path_strips`](crate::Bintje::path_strips).
+///
+/// A strip covers [`Tile::HEIGHT`] pixel rows, and a whole number of tiles horizontally. Within the
+/// strip, coverage is given per pixel by the alpha mask. Between the previous strip in the same
+/// rows (or the viewport's left edge) and the strip, coverage is given per pixel row.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PathStrip<'a> {
+    /// The left edge of the strip in pixels.
+    pub x: u32,
+    /// The top edge of the strip in pixels.
+    pub y: u32,
+    /// The width of the strip in pixels. This is a multiple of [`Tile::WIDTH`].
+    pub width: u32,
+    /// The left edge in pixels of the area between the previous strip and this strip, i.e., the
+    /// right edge of the previous strip in the same rows, or 0 if this is the first strip in its
+    /// rows.
+    pub fill_x: u32,
+    /// The per-row coverage of the pixels between `fill_x` and `x`, where 255 is full coverage.
+    pub pixel_coverage: [u8; Tile::HEIGHT as usize],
+    /// The per-pixel coverage of the strip, in column-major order, i.e., [`Tile::HEIGHT`]
+    /// consecutive bytes per pixel column.
+    pub alpha_mask: &'a [u8],
+}
+
+/// Convert strips to their public representation in pixel coordinates.
+pub(crate) fn path_strips<'a>(
+    strips: &'a [Strip],
+    alpha_masks: &'a [u8],
+) -> impl Iterator<Item = PathStrip<'a>> + 'a {
+    let (tile_width, tile_height) = (u32::from(Tile::WIDTH), u32::from(Tile::HEIGHT));
+    // The row and right edge of the previous strip, in tiles.
+    let mut prev: Option<(u16, u16)> = None;
+    strips.iter().map(move |strip| {
+        let fill_x = match prev {
+            Some((y, end)) if y == strip.y => end,
+            _ => 0,
+        };
+        prev = Some((strip.y, strip.x + strip.width));
+
+        let alpha_idx = strip.alpha_idx as usize;
+        let alpha_mask_len = usize::from(strip.width) * (tile_width * tile_height) as usize;
+        PathStrip {
+            x: u32::from(strip.x) * tile_width,
+            y: u32::from(strip.y) * tile_height,
+            width: u32::from(strip.width) * tile_width,
+            fill_x: u32::from(fill_x) * tile_width,
+            pixel_coverage: strip.pixel_coverage,
+            alpha_mask: &alpha_masks[alpha_idx..alpha_idx + alpha_mask_len],
+        }
+    })
+}
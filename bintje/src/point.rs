@@ -14,6 +14,11 @@ impl From<kurbo::Point> for Point {
 }
 
 impl Point {
+    /// Whether both coordinates of the point are finite.
+    pub(crate) fn is_finite(self) -> bool {
+        self.x.is_finite() && self.y.is_finite()
+    }
+
     /// Rotate the point 90 degrees clockwise in a y-down coordinate system around the origin.
     pub(crate) const fn turn_90(self) -> Self {
         Self {
@@ -0,0 +1,245 @@
+use crate::{strip, tile, Error, Line, Point, Strip, Tile, TileRow, TileSorter, TileSpan};
+
+/// A flattened path that can be filled repeatedly at integer pixel offsets, see
+/// [`Bintje::prepare_path`](crate::Bintje::prepare_path).
+///
+/// The strips and alpha masks generated for the path are cached, such that filling the path again
+/// skips flattening, tile generation and strip generation. As alpha masks are aligned to the tile
+/// grid, strips are generated once for each offset within a tile (e.g., once for offsets that are
+/// a multiple of the tile size), and reused for all offsets with the same position within a tile.
+#[derive(Clone, Debug)]
+pub struct PreparedPath {
+    /// The lines of the path, relative to `origin`.
+    lines: Vec<Line>,
+    /// The top-left of the path's bounding box in pixels, rounded down.
+    origin: (i32, i32),
+    /// The size of the path's bounding box in pixels, measured from `origin` and rounded up.
+    size: (u16, u16),
+    /// The cached strips, per offset within a tile.
+    pub(crate) phases: Vec<PhaseStrips>,
+}
+
+/// The strips of a prepared path placed at an offset within a tile.
+#[derive(Clone, Debug)]
+pub(crate) struct PhaseStrips {
+    /// The offset in pixels within a tile.
+    phase: (u16, u16),
+    /// The strips, with coordinates in tiles relative to the path's origin.
+    strips: Vec<Strip>,
+    /// The alpha masks the strips index into.
+    alpha_masks: Vec<u8>,
+}
+
+/// The scratch buffers used to generate the strips of a prepared path.
+#[derive(Debug, Default)]
+pub(crate) struct PrepareScratch {
+    rows: Vec<TileRow>,
+    spans: Vec<TileSpan>,
+    sorter: TileSorter,
+}
+
+impl PreparedPath {
+    /// The largest extent in pixels of a path that can be prepared.
+    ///
+    /// This leaves room for offsetting the path within a tile.
+    const MAX_SIZE: f32 = (u16::MAX - Tile::WIDTH - Tile::HEIGHT) as f32;
+
+    /// The largest distance in pixels of a prepared path's coordinates from the origin.
+    const MAX_COORDINATE: f32 = (1 << 30) as f32;
+
+    /// A prepared path without geometry.
+    pub(crate) fn empty() -> Self {
+        Self {
+            lines: Vec::new(),
+            origin: (0, 0),
+            size: (0, 0),
+            phases: Vec::new(),
+        }
+    }
+
+    /// Prepare the path from its lines in pixel coordinates. The lines must be finite.
+    pub(crate) fn new(lines: &[Line]) -> Result<Self, Error> {
+        let Some(first) = lines.first() else {
+            return Ok(Self::empty());
+        };
+        let (mut min, mut max) = (first.p0, first.p0);
+        for point in lines.iter().flat_map(|line| [line.p0, line.p1]) {
+            min.x = min.x.min(point.x);
+            min.y = min.y.min(point.y);
+            max.x = max.x.max(point.x);
+            max.y = max.y.max(point.y);
+        }
+
+        let (min_x, min_y) = (min.x.floor(), min.y.floor());
+        if [min.x, min.y, max.x, max.y]
+            .iter()
+            .any(|coordinate| coordinate.abs() > Self::MAX_COORDINATE)
+            || max.x - min_x > Self::MAX_SIZE
+            || max.y - min_y > Self::MAX_SIZE
+        {
+            return Err(Error::PathTooLarge);
+        }
+
+        let origin = Point { x: min_x, y: min_y };
+        #[expect(
+            clippy::cast_possible_truncation,
+            reason = "the bounding box is checked to be in range"
+        )]
+        Ok(Self {
+            lines: lines
+                .iter()
+                .map(|line| Line {
+                    p0: line.p0 - origin,
+                    p1: line.p1 - origin,
+                })
+                .collect(),
+            origin: (min_x as i32, min_y as i32),
+            size: ((max.x - min_x).ceil() as u16, (max.y - min_y).ceil() as u16),
+            phases: Vec::new(),
+        })
+    }
+
+    /// Get the strips for the path placed at `phase` within a tile, generating them if they are
+    /// not cached yet.
+    fn phase_strips(&mut self, phase: (u16, u16), scratch: &mut PrepareScratch) -> &PhaseStrips {
+        if let Some(idx) = self.phases.iter().position(|strips| strips.phase == phase) {
+            return &self.phases[idx];
+        }
+
+        let offset = Point {
+            x: phase.0.into(),
+            y: phase.1.into(),
+        };
+        let lines: Vec<Line> = self
+            .lines
+            .iter()
+            .map(|line| Line {
+                p0: line.p0 + offset,
+                p1: line.p1 + offset,
+            })
+            .collect();
+
+        let width = self.size.0 + phase.0;
+        let row_count = (self.size.1 + phase.1).div_ceil(Tile::HEIGHT) as usize;
+        scratch.rows.resize_with(row_count, TileRow::new);
+        let rows = &mut scratch.rows[..row_count];
+        for row in rows.iter_mut() {
+            row.clear();
+        }
+        tile::generate_tiles(rows, &mut scratch.spans, width, &lines);
+
+        let mut phase_strips = PhaseStrips {
+            phase,
+            strips: Vec::new(),
+            alpha_masks: Vec::new(),
+        };
+        for (y, row) in rows.iter_mut().enumerate() {
+            row.sort(&mut scratch.sorter);
+            strip::generate_strips(
+                row,
+                y as u16,
+                width.div_ceil(Tile::WIDTH),
+                &lines,
+                &mut phase_strips.alpha_masks,
+                &mut phase_strips.strips,
+            );
+        }
+        self.phases.push(phase_strips);
+        self.phases.last().unwrap()
+    }
+}
+
+/// Generate the strips of `path` translated by `offset` in pixels into `strips`, clipping them to
+/// the viewport of `width` by `height` pixels. Their alpha masks are appended to `alpha_masks`.
+pub(crate) fn generate_prepared_strips(
+    path: &mut PreparedPath,
+    offset: (i32, i32),
+    width: u16,
+    height: u16,
+    scratch: &mut PrepareScratch,
+    alpha_masks: &mut Vec<u8>,
+    strips: &mut Vec<Strip>,
+) {
+    if path.lines.is_empty() {
+        return;
+    }
+
+    // The position of the path's origin in pixels, split into a position in tiles and an offset
+    // within that tile.
+    let x = i64::from(path.origin.0) + i64::from(offset.0);
+    let y = i64::from(path.origin.1) + i64::from(offset.1);
+    let (tile_width, tile_height) = (i64::from(Tile::WIDTH), i64::from(Tile::HEIGHT));
+    #[expect(
+        clippy::cast_possible_truncation,
+        reason = "the remainder is less than the tile size"
+    )]
+    let phase = (
+        x.rem_euclid(tile_width) as u16,
+        y.rem_euclid(tile_height) as u16,
+    );
+    let (tile_x, tile_y) = (x.div_euclid(tile_width), y.div_euclid(tile_height));
+
+    let width_in_tiles = i64::from(width.div_ceil(Tile::WIDTH));
+    let height_in_tiles = i64::from(height.div_ceil(Tile::HEIGHT));
+    let mask_len = i64::from(Tile::WIDTH * Tile::HEIGHT);
+
+    let phase_strips = path.phase_strips(phase, scratch);
+    // The row and right edge of the previous strip.
+    let mut prev_row = None;
+    let mut prev_end = i64::MIN;
+    for strip in &phase_strips.strips {
+        let row = i64::from(strip.y) + tile_y;
+        if row < 0 {
+            continue;
+        }
+        if row >= height_in_tiles {
+            break;
+        }
+
+        let start = i64::from(strip.x) + tile_x;
+        let end = start + i64::from(strip.width);
+        if prev_row != Some(row) {
+            prev_row = Some(row);
+            prev_end = i64::MIN;
+        }
+        let area_start = prev_end;
+        prev_end = end;
+        #[expect(
+            clippy::cast_possible_truncation,
+            reason = "coordinates are clipped to the viewport"
+        )]
+        if end <= 0 {
+            // The strip, and the area covered between it and the previous strip, are left of the
+            // viewport.
+            continue;
+        } else if start >= width_in_tiles {
+            // Only the area covered between the previous strip and the strip can be within the
+            // viewport. Fill it up to the viewport's right edge.
+            if area_start < width_in_tiles && strip.pixel_coverage != [0; Tile::HEIGHT as usize] {
+                strips.push(Strip {
+                    x: width_in_tiles as u16,
+                    y: row as u16,
+                    width: 0,
+                    pixel_coverage: strip.pixel_coverage,
+                    alpha_idx: alpha_masks.len() as u32,
+                });
+            }
+        } else {
+            let visible_start = start.max(0);
+            let visible_end = end.min(width_in_tiles);
+            let mask_start = i64::from(strip.alpha_idx) + (visible_start - start) * mask_len;
+            let mask_end = mask_start + (visible_end - visible_start) * mask_len;
+            let alpha_idx = alpha_masks.len() as u32;
+            alpha_masks.extend_from_slice(
+                &phase_strips.alpha_masks[mask_start as usize..mask_end as usize],
+            );
+            strips.push(Strip {
+                x: visible_start as u16,
+                y: row as u16,
+                width: (visible_end - visible_start) as u16,
+                pixel_coverage: strip.pixel_coverage,
+                alpha_idx,
+            });
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use crate::{Strip, Tile};
+
+/// Generate strips for an axis-aligned rectangle, computing the pixel area coverage
+/// analytically.
+///
+/// `rect` is in pixel coordinates and must be clipped to the viewport. Per tile row, this
+/// generates a one-tile strip for the rectangle's left edge and, if the rectangle spans more than
+/// one tile column, a one-tile strip for its right edge. The interior between the two is covered
+/// by the right strip's `pixel_coverage`.
+pub(crate) fn generate_rect_strips(
+    rect: kurbo::Rect,
+    alpha_storage: &mut Vec<u8>,
+    strips: &mut Vec<Strip>,
+) {
+    let x0 = rect.x0 as f32;
+    let y0 = rect.y0 as f32;
+    let x1 = rect.x1 as f32;
+    let y1 = rect.y1 as f32;
+
+    if !(x0 < x1 && y0 < y1) {
+        return;
+    }
+
+    let tile_left_x = (x0 / Tile::WIDTH as f32).floor() as u16;
+    let tile_right_x = ((x1 / Tile::WIDTH as f32).ceil() as u16).saturating_sub(1);
+    let tile_top_y = (y0 / Tile::HEIGHT as f32).floor() as u16;
+    let tile_bottom_y = ((y1 / Tile::HEIGHT as f32).ceil() as u16).saturating_sub(1);
+
+    for row_y in tile_top_y..=tile_bottom_y {
+        let row_top_y = (row_y * Tile::HEIGHT) as f32;
+
+        // The vertical coverage of each pixel row within this tile row.
+        let mut row_coverage = [0.; Tile::HEIGHT as usize];
+        for (y_px, coverage) in row_coverage.iter_mut().enumerate() {
+            let px_top_y = row_top_y + y_px as f32;
+            *coverage = (y1.min(px_top_y + 1.) - y0.max(px_top_y)).clamp(0., 1.);
+        }
+
+        strips.push(Strip {
+            x: tile_left_x,
+            y: row_y,
+            width: 1,
+            pixel_coverage: [0; Tile::HEIGHT as usize],
+            alpha_idx: alpha_storage.len() as u32,
+        });
+        push_alpha_mask(tile_left_x, x0, x1, &row_coverage, alpha_storage);
+
+        if tile_right_x > tile_left_x {
+            strips.push(Strip {
+                x: tile_right_x,
+                y: row_y,
+                width: 1,
+                pixel_coverage: row_coverage
+                    .map(|coverage| (coverage * u8::MAX as f32).round() as u8),
+                alpha_idx: alpha_storage.len() as u32,
+            });
+            push_alpha_mask(tile_right_x, x0, x1, &row_coverage, alpha_storage);
+        }
+    }
+}
+
+/// Push the alpha mask of the tile at column `tile_x`, where the rectangle spans `x0..x1`
+/// horizontally and the tile row's vertical coverage is given by `row_coverage`.
+fn push_alpha_mask(
+    tile_x: u16,
+    x0: f32,
+    x1: f32,
+    row_coverage: &[f32; Tile::HEIGHT as usize],
+    alpha_storage: &mut Vec<u8>,
+) {
+    let tile_left_x = (tile_x * Tile::WIDTH) as f32;
+    for x_px in 0..Tile::WIDTH {
+        let px_left_x = tile_left_x + x_px as f32;
+        let column_coverage = (x1.min(px_left_x + 1.) - x0.max(px_left_x)).clamp(0., 1.);
+        for coverage in row_coverage {
+            alpha_storage.push((column_coverage * coverage * u8::MAX as f32).round() as u8);
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use std::time::Duration;
+
+use crate::Command;
+
+/// Statistics of the work done to generate draw commands since the scene was last cleared, see
+/// [`Bintje::stats`](crate::Bintje::stats).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RenderStats {
+    /// Time spent flattening fills.
+    pub flattening_time: Duration,
+    /// Time spent expanding and flattening strokes.
+    pub flattening_stroke_time: Duration,
+    /// Time spent generating tiles from lines.
+    pub tile_generation_time: Duration,
+    /// Time spent sorting tiles.
+    pub tile_sorting_time: Duration,
+    /// Time spent generating strips from tiles.
+    pub strip_generation_time: Duration,
+
+    /// The number of lines generated by flattening.
+    pub lines: usize,
+    /// The number of tiles generated from the lines.
+    pub tiles: usize,
+    /// The number of strips generated from the tiles.
+    pub strips: usize,
+    /// The number of bytes of alpha masks generated for the strips.
+    pub alpha_mask_bytes: usize,
+    /// The number of draws skipped as their bounding box is outside the viewport.
+    pub culled_draws: usize,
+    /// The number of subpaths skipped as their bounding box is outside the viewport, not counting
+    /// those of culled draws.
+    pub culled_subpaths: usize,
+
+    /// The number of commands per wide tile, in the same order as
+    /// [`Commands::wide_tiles`](crate::Commands::wide_tiles).
+    pub wide_tile_commands: Vec<CommandCounts>,
+}
+
+/// The number of wide tile commands, per command variant.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CommandCounts {
+    /// The number of [`Command::Sample`] commands.
+    pub sample: usize,
+    /// The number of [`Command::SparseSample`] commands.
+    pub sparse_sample: usize,
+    /// The number of [`Command::SparseFill`] commands.
+    pub sparse_fill: usize,
+    /// The number of [`Command::PushClip`] and [`Command::PopClip`] commands.
+    pub clip: usize,
+}
+
+impl RenderStats {
+    /// Create statistics for the given number of wide tiles.
+    pub(crate) fn new(wide_tiles: usize) -> Self {
+        Self {
+            wide_tile_commands: vec![CommandCounts::default(); wide_tiles],
+            ..Default::default()
+        }
+    }
+
+    /// Reset all statistics to zero.
+    pub(crate) fn reset(&mut self) {
+        let mut wide_tile_commands = std::mem::take(&mut self.wide_tile_commands);
+        wide_tile_commands.fill(CommandCounts::default());
+        *self = Self {
+            wide_tile_commands,
+            ..Default::default()
+        };
+    }
+
+    /// The number of commands over all wide tiles.
+    pub fn commands(&self) -> CommandCounts {
+        let mut total = CommandCounts::default();
+        for counts in &self.wide_tile_commands {
+            total.sample += counts.sample;
+            total.sparse_sample += counts.sparse_sample;
+            total.sparse_fill += counts.sparse_fill;
+            total.clip += counts.clip;
+        }
+        total
+    }
+}
+
+impl CommandCounts {
+    /// The number of commands of all variants.
+    pub fn total(&self) -> usize {
+        self.sample + self.sparse_sample + self.sparse_fill + self.clip
+    }
+
+    /// Count the command.
+    pub(crate) fn count(&mut self, command: &Command) {
+        match command {
+            Command::Sample(_) => self.sample += 1,
+            Command::SparseSample(_) => self.sparse_sample += 1,
+            Command::SparseFill(_) => self.sparse_fill += 1,
+            Command::PushClip(_) | Command::PopClip(_) => self.clip += 1,
+        }
+    }
+}
@@ -0,0 +1,80 @@
+//! Checks shared by the tests of the [`FineRasterizer`] backends.
+//!
+//! This requires the `testing` feature.
+
+use peniko::color::PremulRgba8;
+
+use crate::{cpu_rasterize, Bintje, Error, FineRasterizer, RenderTarget};
+
+/// The width of the render target [`check_rasterizer`] rasterizes to.
+pub const TARGET_WIDTH: u16 = 300;
+/// The height of the render target [`check_rasterizer`] rasterizes to.
+pub const TARGET_HEIGHT: u16 = 70;
+
+/// Check a rasterizer for a [`TARGET_WIDTH`] by [`TARGET_HEIGHT`] pixel render target against
+/// [`cpu_rasterize`], allowing each channel of every pixel to differ by at most `tolerance`.
+///
+/// This rasterizes a frame in full, then a second frame with damage, and checks that a
+/// mismatched image size is rejected.
+///
+/// # Panics
+///
+/// Panics if the rasterizer fails a check.
+pub fn check_rasterizer(rasterizer: &mut dyn FineRasterizer, tolerance: u8) {
+    const PIXELS: usize = TARGET_WIDTH as usize * TARGET_HEIGHT as usize;
+
+    let mut renderer = Bintje::new(TARGET_WIDTH, TARGET_HEIGHT);
+    let mut rasterize = |renderer: &Bintje, img: &mut [PremulRgba8], damage| {
+        rasterizer.rasterize(
+            renderer.commands(),
+            RenderTarget {
+                width: TARGET_WIDTH,
+                height: TARGET_HEIGHT,
+                pixels: img,
+                damage,
+            },
+        )
+    };
+
+    renderer.fill_shape(
+        kurbo::Circle::new((30., 30.), 20.3),
+        peniko::color::palette::css::SEA_GREEN,
+    );
+    let mut img = vec![PremulRgba8::from_u32(0); PIXELS];
+    rasterize(&renderer, &mut img, None).unwrap();
+    let damage = renderer.damage();
+
+    renderer.fill_shape(
+        kurbo::Rect::new(250.5, 50.5, 260.5, 60.5),
+        peniko::color::palette::css::ORANGE_RED,
+    );
+    let damage_after = renderer.damage();
+    assert_ne!(damage, damage_after);
+    rasterize(&renderer, &mut img, Some(&damage_after)).unwrap();
+    let commands = renderer.commands();
+    let mut expected = vec![PremulRgba8::from_u32(0); PIXELS];
+    cpu_rasterize(
+        TARGET_WIDTH,
+        TARGET_HEIGHT,
+        &mut expected,
+        commands.alpha_masks,
+        commands.wide_tiles,
+    );
+    for (pixel, expected) in img.iter().zip(&expected) {
+        let channels = pixel.to_u8_array().into_iter();
+        for (channel, expected_channel) in channels.zip(expected.to_u8_array()) {
+            assert!(
+                channel.abs_diff(expected_channel) <= tolerance,
+                "rasterizing should match `cpu_rasterize`: {pixel:?} != {expected:?}"
+            );
+        }
+    }
+
+    assert_eq!(
+        rasterize(&renderer, &mut img[1..], None),
+        Err(Error::ImageSizeMismatch {
+            expected: PIXELS,
+            actual: PIXELS - 1
+        })
+    );
+}
@@ -5,7 +5,7 @@ use color::PremulRgba8;
 use image::ImageEncoder;
 use kurbo::Shape;
 
-use crate::{wide_tile, Bintje};
+use crate::{wide_tile, Bintje, Diagnostic};
 
 // Creates a new instance of TestEnv and put current function name in constructor
 #[macro_export]
@@ -54,6 +54,17 @@ impl TestEnv {
             .expect("Call `TestEnv::set_size` first.")
     }
 
+    /// The alpha of the pixel at `(x, y)` of the image last rasterized by
+    /// [`TestEnv::rasterize_to_png`].
+    pub fn alpha(&self, x: u16, y: u16) -> u8 {
+        let (width, _) = self
+            .bintje
+            .as_ref()
+            .expect("Call `TestEnv::set_size` first.")
+            .size();
+        self.img[usize::from(y) * usize::from(width) + usize::from(x)].a
+    }
+
     /// Rasterize the current render context to a PNG file, with the name based on the test
     /// environment.
     pub fn rasterize_to_png(&mut self) {
@@ -62,26 +73,16 @@ impl TestEnv {
             .as_ref()
             .expect("Call `TestEnv::set_size` first.");
         let (width, height) = renderer.size();
-        self.img.clear();
-        self.img
-            .resize(width as usize * height as usize, PremulRgba8::zeroed());
-        let commands = renderer.commands();
-        wide_tile::cpu_rasterize(
-            width,
-            height,
-            &mut self.img,
-            commands.alpha_masks,
-            commands.wide_tiles,
-        );
+        self.img = rasterize(renderer);
 
         let img_name = if self.counter == 0 {
             format!("{}.png", &self.name)
         } else {
             format!("{}-{}.png", &self.name, self.counter)
         };
-        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
-            .join("tests/current")
-            .join(img_name);
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/current");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(img_name);
         self.counter.checked_add(1).unwrap();
 
         let mut file = std::fs::OpenOptions::new()
@@ -102,6 +103,36 @@ impl TestEnv {
     }
 }
 
+/// Rasterize the commands of `renderer` to a pixel buffer of the renderer's size.
+fn rasterize(renderer: &Bintje) -> Vec<PremulRgba8> {
+    let (width, height) = renderer.size();
+    let commands = renderer.commands();
+    let mut img = vec![PremulRgba8::zeroed(); usize::from(width) * usize::from(height)];
+    wide_tile::cpu_rasterize(
+        width,
+        height,
+        &mut img,
+        commands.alpha_masks,
+        commands.wide_tiles,
+    );
+    img
+}
+
+/// Assert that `renderer` has not generated any commands.
+#[track_caller]
+fn assert_no_commands(renderer: &Bintje) {
+    let wide_tiles = renderer.commands().wide_tiles;
+    if let Some(idx) = wide_tiles
+        .iter()
+        .position(|wide_tile| !wide_tile.commands.is_empty())
+    {
+        panic!(
+            "expected no commands, but wide tile {idx} has {:?}",
+            wide_tiles[idx].commands
+        );
+    }
+}
+
 #[test]
 fn triangular_stroke() {
     let mut env = testenv!();
@@ -156,3 +187,1455 @@ fn overflow_left_viewport() {
     );
     env.rasterize_to_png();
 }
+
+#[test]
+fn overflow_right_viewport() {
+    let mut env = testenv!();
+    env.set_size(64, 64);
+
+    let renderer = env.renderer();
+    renderer.stroke(
+        kurbo::Rect::new(13.5, 5.5, 64.5, 40.5).path_elements(f64::NAN),
+        &kurbo::Stroke {
+            width: 1.0,
+            ..kurbo::Stroke::default()
+        },
+        color::palette::css::ORANGE_RED,
+    );
+    env.rasterize_to_png();
+
+    // The top and bottom edges run up to the rightmost column, the right edge is outside the
+    // viewport.
+    for y in [5, 40] {
+        assert_eq!(env.alpha(63, y), 255);
+    }
+    for y in 6..40 {
+        assert_eq!(env.alpha(63, y), 0);
+        assert_eq!(env.alpha(13, y), 255);
+    }
+}
+
+#[test]
+fn overflow_top_viewport() {
+    let mut env = testenv!();
+    env.set_size(64, 64);
+
+    let renderer = env.renderer();
+    renderer.stroke(
+        kurbo::Rect::new(5.5, -0.5, 40.5, 50.5).path_elements(f64::NAN),
+        &kurbo::Stroke {
+            width: 1.0,
+            ..kurbo::Stroke::default()
+        },
+        color::palette::css::ORANGE_RED,
+    );
+    env.rasterize_to_png();
+
+    // The left and right edges run up to the top row, the top edge is outside the viewport.
+    for x in [5, 40] {
+        assert_eq!(env.alpha(x, 0), 255);
+    }
+    for x in 6..40 {
+        assert_eq!(env.alpha(x, 0), 0);
+        assert_eq!(env.alpha(x, 50), 255);
+    }
+}
+
+#[test]
+fn overflow_bottom_viewport() {
+    let mut env = testenv!();
+    env.set_size(64, 64);
+
+    let renderer = env.renderer();
+    renderer.stroke(
+        kurbo::Rect::new(5.5, 13.5, 40.5, 64.5).path_elements(f64::NAN),
+        &kurbo::Stroke {
+            width: 1.0,
+            ..kurbo::Stroke::default()
+        },
+        color::palette::css::ORANGE_RED,
+    );
+    env.rasterize_to_png();
+
+    // The left and right edges run up to the bottom row, the bottom edge is outside the
+    // viewport.
+    for x in [5, 40] {
+        assert_eq!(env.alpha(x, 63), 255);
+    }
+    for x in 6..40 {
+        assert_eq!(env.alpha(x, 63), 0);
+        assert_eq!(env.alpha(x, 13), 255);
+    }
+}
+
+#[test]
+fn overflow_all_viewport_edges() {
+    let mut env = testenv!();
+    env.set_size(62, 62);
+
+    let renderer = env.renderer();
+    renderer.fill_shape(
+        kurbo::Circle::new((31., 31.), 40.),
+        color::palette::css::ORANGE_RED,
+    );
+    env.rasterize_to_png();
+
+    // The circle covers the middle of every edge row and column, but not the corners.
+    for i in 10..52 {
+        assert_eq!(env.alpha(i, 0), 255);
+        assert_eq!(env.alpha(i, 61), 255);
+        assert_eq!(env.alpha(0, i), 255);
+        assert_eq!(env.alpha(61, i), 255);
+    }
+    for (x, y) in [(0, 0), (61, 0), (0, 61), (61, 61)] {
+        assert_eq!(env.alpha(x, y), 0);
+    }
+}
+
+#[test]
+fn cull_outside_viewport() {
+    let mut env = testenv!();
+    env.set_size(64, 64);
+
+    let renderer = env.renderer();
+    for rect in [
+        kurbo::Rect::new(10., -30., 50., -2.),
+        kurbo::Rect::new(10., 66., 50., 90.),
+        kurbo::Rect::new(66., 10., 90., 50.),
+    ] {
+        renderer.fill_shape(rect, color::palette::css::ORANGE_RED);
+    }
+
+    // Geometry outside the viewport should not generate commands.
+    assert_no_commands(renderer);
+}
+
+#[test]
+fn bounding_box_culling() {
+    let mut renderer = Bintje::new(64, 64);
+    renderer.fill_shape(
+        kurbo::Circle::new((-40., 30.), 20.),
+        color::palette::css::ORANGE_RED,
+    );
+    renderer.stroke(
+        kurbo::Line::new((10., 80.), (50., 80.)).path_elements(0.1),
+        &kurbo::Stroke::new(4.),
+        color::palette::css::ORANGE_RED,
+    );
+    assert_eq!(renderer.stats().culled_draws, 2);
+    assert_eq!(renderer.stats().lines, 0);
+
+    // A subpath left of the viewport does not change the winding number of the subpath within
+    // it.
+    let on_screen = kurbo::Circle::new((30., 30.), 20.).to_path(0.1);
+    let mut partially_on_screen = kurbo::Circle::new((-40., 30.), 20.).to_path(0.1);
+    partially_on_screen.extend(on_screen.iter());
+    renderer.clear();
+    renderer.fill_shape(&partially_on_screen, color::palette::css::ORANGE_RED);
+    assert_eq!(renderer.stats().culled_subpaths, 1);
+    let culled = rasterize(&renderer);
+    renderer.clear();
+    renderer.fill_shape(&on_screen, color::palette::css::ORANGE_RED);
+    assert!(culled == rasterize(&renderer));
+
+    // The miter join of a stroke extends beyond the stroke's half width. Only the join's tip is
+    // within the viewport.
+    renderer.clear();
+    let mut stroke = kurbo::BezPath::new();
+    stroke.move_to((20., 100.));
+    stroke.line_to((30., 66.));
+    stroke.line_to((40., 100.));
+    renderer.stroke(
+        stroke,
+        &kurbo::Stroke::new(4.).with_join(kurbo::Join::Miter),
+        color::palette::css::ORANGE_RED,
+    );
+    assert_eq!(renderer.stats().culled_draws, 0);
+    assert!(rasterize(&renderer).iter().any(|pixel| pixel.a > 0));
+}
+
+#[test]
+fn non_finite_geometry() {
+    let mut env = testenv!();
+    env.set_size(64, 64);
+
+    let renderer = env.renderer();
+    let mut path = kurbo::BezPath::new();
+    path.move_to((10., 10.));
+    path.line_to((f64::NAN, 40.));
+    path.line_to((50., 50.));
+    path.close_path();
+    renderer.fill_shape(&path, color::palette::css::ORANGE_RED);
+
+    let mut path = kurbo::BezPath::new();
+    path.move_to((10., 10.));
+    path.curve_to((f64::INFINITY, 0.), (30., 60.), (50., 50.));
+    path.close_path();
+    renderer.stroke(
+        &path,
+        &kurbo::Stroke::default(),
+        color::palette::css::ORANGE_RED,
+    );
+
+    // Finite in `f64`, but overflows `f32`.
+    renderer.fill_shape(
+        kurbo::Triangle::new((0., 0.), (1e300, 0.), (0., 1e300)),
+        color::palette::css::ORANGE_RED,
+    );
+
+    assert_eq!(
+        renderer.diagnostics(),
+        &[Diagnostic::NonFiniteGeometry; 3],
+        "each non-finite draw should be reported"
+    );
+    // Non-finite geometry should not generate commands.
+    assert_no_commands(renderer);
+
+    // Subsequent draws are unaffected.
+    renderer.fill_shape(
+        kurbo::Circle::new((32., 32.), 20.),
+        color::palette::css::ORANGE_RED,
+    );
+    env.rasterize_to_png();
+}
+
+#[test]
+fn degenerate_transform() {
+    let mut env = testenv!();
+    env.set_size(64, 64);
+
+    let renderer = env.renderer();
+    renderer.push_transform(kurbo::Affine::scale(f64::NAN));
+    renderer.fill_shape(
+        kurbo::Circle::new((32., 32.), 20.),
+        color::palette::css::ORANGE_RED,
+    );
+    renderer.pop_transform();
+
+    renderer.push_transform(kurbo::Affine::scale_non_uniform(1., 0.));
+    renderer.fill_shape(
+        kurbo::Circle::new((32., 32.), 20.),
+        color::palette::css::ORANGE_RED,
+    );
+    renderer.pop_transform();
+
+    assert_eq!(
+        renderer.diagnostics(),
+        &[Diagnostic::DegenerateTransform; 2],
+        "each degenerate transform should be reported"
+    );
+    // Draws under a degenerate transform should not generate commands.
+    assert_no_commands(renderer);
+}
+
+#[test]
+fn flattening_tolerance() {
+    let mut env = testenv!();
+    env.set_size(64, 64);
+
+    let renderer = env.renderer();
+    renderer.set_tolerance(2.);
+    renderer.fill_shape(
+        kurbo::Circle::new((20., 20.), 16.),
+        color::palette::css::ORANGE_RED,
+    );
+    renderer.fill_shape_with_tolerance(
+        kurbo::Circle::new((44., 44.), 16.),
+        color::palette::css::ORANGE_RED,
+        0.05,
+    );
+    env.rasterize_to_png();
+}
+
+#[test]
+fn flattening_tolerance_line_count() {
+    let circle = kurbo::Circle::new((32., 32.), 16.);
+    let stroke = kurbo::Stroke::new(2.);
+    let lines = |draw: &dyn Fn(&mut Bintje, f64), tolerance| {
+        let mut renderer = Bintje::new(64, 64);
+        draw(&mut renderer, tolerance);
+        renderer.stats().lines
+    };
+    let fill = |renderer: &mut Bintje, tolerance| {
+        renderer.fill_shape_with_tolerance(circle, color::palette::css::ORANGE_RED, tolerance);
+    };
+    let stroke = |renderer: &mut Bintje, tolerance| {
+        renderer.stroke_with_tolerance(
+            circle.path_elements(0.1),
+            &stroke,
+            color::palette::css::ORANGE_RED,
+            tolerance,
+        );
+    };
+    let set_tolerance = |renderer: &mut Bintje, tolerance| {
+        renderer.set_tolerance(tolerance);
+        renderer.fill_shape(circle, color::palette::css::ORANGE_RED);
+    };
+    for draw in [&fill as &dyn Fn(&mut Bintje, f64), &stroke, &set_tolerance] {
+        let (fine, default, coarse) = (lines(draw, 0.05), lines(draw, 0.25), lines(draw, 2.));
+        assert!(
+            fine > default && default > coarse && coarse > 0,
+            "coarser tolerances should flatten to fewer lines, got {fine}, {default} and {coarse}"
+        );
+    }
+}
+
+#[test]
+fn invalid_tolerance() {
+    use crate::Error;
+
+    let mut renderer = Bintje::new(64, 64);
+    let circle = kurbo::Circle::new((32., 32.), 16.);
+    for tolerance in [0., -1., f64::NAN, f64::INFINITY] {
+        assert!(matches!(
+            renderer.try_fill_shape_with_tolerance(
+                circle,
+                color::palette::css::ORANGE_RED,
+                tolerance
+            ),
+            Err(Error::InvalidTolerance(_))
+        ));
+        assert!(matches!(
+            renderer.try_stroke_with_tolerance(
+                circle.path_elements(0.1),
+                &kurbo::Stroke::new(2.),
+                color::palette::css::ORANGE_RED,
+                tolerance,
+            ),
+            Err(Error::InvalidTolerance(_))
+        ));
+        assert!(matches!(
+            renderer.try_set_tolerance(tolerance),
+            Err(Error::InvalidTolerance(_))
+        ));
+    }
+    assert_eq!(renderer.tolerance(), Bintje::DEFAULT_TOLERANCE);
+    assert_eq!(renderer.stats().lines, 0);
+    assert_no_commands(&renderer);
+}
+
+#[test]
+fn transform_scale() {
+    use kurbo::Affine;
+
+    let mut renderer = Bintje::new(64, 64);
+    for (transform, expected_scale) in [
+        (Affine::IDENTITY, 1.),
+        (Affine::scale(3.), 3.),
+        (Affine::scale_non_uniform(0.5, 2.), 2.),
+        (Affine::rotate(std::f64::consts::FRAC_PI_2), 1.),
+        (Affine::rotate(1.) * Affine::scale(2.), 2.),
+        (Affine::skew(1., 0.), 0.5 * (1. + 5_f64.sqrt())),
+        (Affine::translate((100., -50.)), 1.),
+    ] {
+        renderer.push_transform(transform);
+        assert!(
+            (renderer.current_scale - expected_scale).abs() < 1e-9,
+            "scale of {transform:?} should be {expected_scale}, got {}",
+            renderer.current_scale
+        );
+        renderer.pop_transform();
+    }
+}
+
+#[test]
+fn rotated_shape() {
+    let mut env = testenv!();
+    env.set_size(64, 64);
+
+    let transform =
+        kurbo::Affine::translate((32., 32.)) * kurbo::Affine::rotate(std::f64::consts::FRAC_PI_2);
+    let ellipse = kurbo::Ellipse::new((0., 0.), (24., 12.), 0.);
+    let renderer = env.renderer();
+    renderer.push_transform(transform);
+    renderer.fill_shape(ellipse, color::palette::css::ORANGE_RED);
+    renderer.pop_transform();
+    env.rasterize_to_png();
+
+    // The shape transformed by hand renders the same under the identity transform.
+    let mut expected = Bintje::new(64, 64);
+    expected.fill_shape(transform * ellipse, color::palette::css::ORANGE_RED);
+    let renderer = env.renderer();
+    assert!(
+        rasterize(renderer) == rasterize(&expected),
+        "rotated shape should match the shape transformed by hand"
+    );
+}
+
+#[test]
+fn fill_polygon() {
+    let mut env = testenv!();
+    env.set_size(64, 64);
+
+    let renderer = env.renderer();
+    let star: Vec<kurbo::Point> = (0..10)
+        .map(|idx| {
+            let angle = idx as f64 * std::f64::consts::PI / 5.;
+            let radius = if idx % 2 == 0 { 28. } else { 12. };
+            kurbo::Point::new(32. + radius * angle.sin(), 32. - radius * angle.cos())
+        })
+        .collect();
+    renderer.fill_polygon(&star, color::palette::css::ORANGE_RED);
+    env.rasterize_to_png();
+}
+
+#[test]
+fn fill_polygon_matches_fill_shape() {
+    let rect = kurbo::Rect::new(5.5, 7.25, 50.75, 40.5);
+
+    let mut shape_renderer = Bintje::new(64, 64);
+    // Convert to a path, as rectangles take a fast path.
+    shape_renderer.fill_shape(rect.to_path(0.1), color::palette::css::ORANGE_RED);
+
+    let mut polygon_renderer = Bintje::new(64, 64);
+    polygon_renderer.fill_polygon(
+        &[
+            (rect.x0, rect.y0).into(),
+            (rect.x1, rect.y0).into(),
+            (rect.x1, rect.y1).into(),
+            (rect.x0, rect.y1).into(),
+        ],
+        color::palette::css::ORANGE_RED,
+    );
+
+    let mut lines_renderer = Bintje::new(64, 64);
+    lines_renderer.fill_lines(
+        &[
+            kurbo::Line::new((rect.x0, rect.y1), (rect.x0, rect.y0)),
+            kurbo::Line::new((rect.x0, rect.y0), (rect.x1, rect.y0)),
+            kurbo::Line::new((rect.x1, rect.y1), (rect.x0, rect.y1)),
+            kurbo::Line::new((rect.x1, rect.y0), (rect.x1, rect.y1)),
+        ],
+        color::palette::css::ORANGE_RED,
+    );
+
+    let shape = shape_renderer.commands();
+    let shape_img = rasterize(&shape_renderer);
+    for renderer in [&polygon_renderer, &lines_renderer] {
+        let commands = renderer.commands();
+        assert_eq!(
+            shape.alpha_masks, commands.alpha_masks,
+            "alpha masks should match those of the equivalent shape"
+        );
+        for (a, b) in shape.wide_tiles.iter().zip(commands.wide_tiles) {
+            assert_eq!(
+                a.commands, b.commands,
+                "commands should match those of the equivalent shape"
+            );
+        }
+        assert!(
+            rasterize(renderer) == shape_img,
+            "pixels should match those of the equivalent shape"
+        );
+    }
+}
+
+#[test]
+fn fill_rect() {
+    use crate::{Command, Tile};
+
+    let mut env = testenv!();
+    env.set_size(64, 64);
+
+    let renderer = env.renderer();
+    let (x0, y0, x1, y1) = (4.25, 6.5, 59.75, 20.);
+    renderer.fill_rect(
+        kurbo::Rect::new(x0, y0, x1, y1),
+        color::palette::css::ORANGE_RED,
+    );
+
+    // Every tile row the rectangle touches samples its left and right edge tiles, and fills the
+    // interior in between, unless the rectangle's top or bottom edge crosses the row.
+    let (tile_width, tile_height) = (f64::from(Tile::WIDTH), f64::from(Tile::HEIGHT));
+    let left_x = (x0 / tile_width).floor() as u16;
+    let right_x = (x1 / tile_width).ceil() as u16 - 1;
+    let top_y = (y0 / tile_height).floor() as usize;
+    let bottom_y = (y1 / tile_height).ceil() as usize - 1;
+    let mut filled_rows = 0;
+    for (y, wide_tile) in renderer.commands().wide_tiles.iter().enumerate() {
+        let commands: Vec<_> = wide_tile
+            .commands
+            .iter()
+            .map(|command| match command {
+                Command::Sample(sample) => ("sample", sample.x, sample.width),
+                Command::SparseSample(sample) => ("sparse sample", sample.x, sample.width),
+                Command::SparseFill(fill) => ("sparse fill", fill.x, fill.width),
+                Command::PushClip(()) | Command::PopClip(()) => unreachable!(),
+            })
+            .collect();
+        if y < top_y || y > bottom_y {
+            assert!(commands.is_empty(), "row {y} is outside the rectangle");
+            continue;
+        }
+        let interior = if y as f64 * tile_height >= y0 && (y + 1) as f64 * tile_height <= y1 {
+            filled_rows += 1;
+            "sparse fill"
+        } else {
+            "sparse sample"
+        };
+        assert_eq!(
+            commands,
+            [
+                ("sample", left_x, 1),
+                (interior, left_x + 1, right_x - left_x - 1),
+                ("sample", right_x, 1),
+            ],
+            "commands of row {y}"
+        );
+    }
+    assert!(filled_rows > 0);
+
+    renderer.fill_rect(
+        kurbo::Rect::new(-10., 30., 20., 80.),
+        color::palette::css::BLUE.with_alpha(0.5),
+    );
+    renderer.fill_rect(
+        kurbo::Rect::new(40.5, 30.5, 41.5, 31.5),
+        color::palette::css::GREEN,
+    );
+    env.rasterize_to_png();
+}
+
+#[test]
+fn fill_rect_matches_fill_shape() {
+    use kurbo::{Affine, Rect};
+
+    for (transform, rect) in [
+        (Affine::IDENTITY, Rect::new(4.25, 6.5, 59.75, 20.)),
+        (Affine::IDENTITY, Rect::new(-10., -3.3, 20.1, 80.)),
+        (Affine::IDENTITY, Rect::new(50.2, 10., 90., 31.7)),
+        (Affine::IDENTITY, Rect::new(5.5, 5.5, 6.25, 6.)),
+        (Affine::scale(1.5), Rect::new(10.1, 3.3, 20.6, 30.)),
+        (
+            Affine::translate((60., 0.)) * Affine::rotate(std::f64::consts::FRAC_PI_2),
+            Rect::new(5.2, 3.3, 40.6, 30.),
+        ),
+        // Rectangles that are not axis-aligned are filled as paths.
+        (
+            Affine::rotate_about(0.3, kurbo::Point::new(35., 25.)),
+            Rect::new(15.2, 10.3, 50.6, 35.),
+        ),
+    ] {
+        let mut renderer = Bintje::new(70, 50);
+        renderer.push_transform(transform);
+        renderer
+            .try_fill_rect(rect, color::palette::css::ORANGE_RED)
+            .unwrap();
+        let fast = rasterize(&renderer);
+        renderer.clear();
+        renderer.push_transform(transform);
+        renderer.fill_shape(rect.to_path(0.1), color::palette::css::ORANGE_RED);
+        let path = rasterize(&renderer);
+
+        for (fast, path) in fast.iter().zip(&path) {
+            for (fast, path) in fast.to_u8_array().into_iter().zip(path.to_u8_array()) {
+                assert!(
+                    fast.abs_diff(path) <= 1,
+                    "rectangle fast path should match path rendering for {rect:?} under {transform:?}"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn exact_tile_traversal() {
+    use crate::{tile, Line, Tile, TileRow};
+
+    // Lines in tile coordinates, and the `(x, y)` tiles they are expected to pass through.
+    for (line, expected) in [
+        // A diagonal through tile corners only passes through the tiles on the diagonal.
+        (
+            kurbo::Line::new((0., 0.), (4., 4.)),
+            vec![(0, 0), (1, 1), (2, 2), (3, 3)],
+        ),
+        // A shallow line within a single row.
+        (
+            kurbo::Line::new((0.25, 0.25), (7.75, 0.75)),
+            (0..8).map(|x| (x, 0)).collect(),
+        ),
+        // A steep line within a single column.
+        (
+            kurbo::Line::new((1.25, 0.25), (1.75, 5.75)),
+            (0..6).map(|y| (1, y)).collect(),
+        ),
+        // A line crossing the viewport's left and bottom edges.
+        (
+            kurbo::Line::new((-1.5, 2.5), (2.5, 10.5)),
+            vec![(0, 5), (0, 6), (0, 7), (1, 7)],
+        ),
+    ] {
+        let mut rows = vec![TileRow::new(); 8];
+        let line = kurbo::Affine::scale_non_uniform(Tile::WIDTH.into(), Tile::HEIGHT.into()) * line;
+        tile::generate_tiles(
+            &mut rows,
+            &mut Vec::new(),
+            8 * Tile::WIDTH,
+            &[Line::from_kurbo(line)],
+        );
+
+        let mut tiles: Vec<(u16, u16)> = rows
+            .iter()
+            .enumerate()
+            .flat_map(|(y, row)| row.tiles.iter().map(move |tile| (tile.x, y as u16)))
+            .collect();
+        tiles.sort_unstable_by_key(|&(x, y)| (y, x));
+        assert_eq!(
+            tiles, expected,
+            "{line:?} should pass through exactly the expected tiles"
+        );
+    }
+}
+
+#[test]
+fn nearly_horizontal_lines() {
+    use crate::{point::Point, tile, Line, TileRow};
+
+    /// Tile the polygon, returning the winding of every row.
+    fn windings(polygon: [(f32, f32); 4]) -> Vec<i32> {
+        let point = |(x, y)| Point { x, y };
+        let lines: Vec<Line> = (0..polygon.len())
+            .map(|idx| Line {
+                p0: point(polygon[idx]),
+                p1: point(polygon[(idx + 1) % polygon.len()]),
+            })
+            .collect();
+        let mut rows = vec![TileRow::new(); 8];
+        tile::generate_tiles(&mut rows, &mut Vec::new(), 64, &lines);
+        rows.iter().map(|row| row.winding).collect()
+    }
+
+    // The first edge is so nearly horizontal that its slope overflows. It crosses the top edge of
+    // the first row left of the viewport, so it contributes to that row's winding, like the
+    // first edge of the steeper polygon does.
+    let nearly_horizontal = windings([(-10., -1e-39), (-5., 1e-39), (40., 20.), (-10., 20.)]);
+    let steeper = windings([(-10., -1.), (-5., 1.), (40., 20.), (-10., 20.)]);
+    assert_eq!(nearly_horizontal, steeper);
+}
+
+#[test]
+fn tile_sorting() {
+    use crate::{tile, Line, Tile, TileRow, TileSorter};
+
+    // Overlapping lines in tile coordinates, such that tiles of later lines sort before tiles of
+    // earlier lines.
+    let lines = [
+        kurbo::Line::new((7.5, 0.25), (0.5, 0.75)),
+        kurbo::Line::new((5., 0.125), (1.5, 0.875)),
+        kurbo::Line::new((0.25, 0.), (0.75, 1.)),
+        kurbo::Line::new((3.25, 0.), (3.25, 1.)),
+    ]
+    .map(|line| {
+        Line::from_kurbo(
+            kurbo::Affine::scale_non_uniform(Tile::WIDTH.into(), Tile::HEIGHT.into()) * line,
+        )
+    });
+
+    let mut rows = vec![TileRow::new(); 1];
+    tile::generate_tiles(&mut rows, &mut Vec::new(), 8 * Tile::WIDTH, &lines);
+    rows[0].sort(&mut TileSorter::default());
+
+    let tiles: Vec<(u16, u32)> = rows[0]
+        .tiles
+        .iter()
+        .map(|tile| (tile.x, tile.line_idx))
+        .collect();
+    let mut expected = tiles.clone();
+    expected.sort_unstable();
+    assert_eq!(
+        tiles, expected,
+        "tiles should be sorted by x, with tiles of equal x in line order"
+    );
+    assert_eq!(tiles.len(), 8 + 4 + 1 + 1);
+}
+
+#[test]
+fn coverage_to_alpha() {
+    let mut coverages = vec![
+        0.,
+        -0.,
+        0.5 / 255.,
+        -0.5 / 255.,
+        f32::from_bits((0.5_f32 / 255.).to_bits() - 1),
+        1.,
+        -1.,
+        2.5,
+        1e10,
+        f32::INFINITY,
+        f32::NEG_INFINITY,
+        f32::NAN,
+    ];
+    coverages.extend((0..=2_000).map(|i| i as f32 / 1_000. - 1.));
+
+    for coverage in coverages {
+        assert_eq!(
+            crate::strip::coverage_to_alpha(coverage),
+            (coverage.abs() * u8::MAX as f32).round() as u8,
+            "alpha of coverage {coverage} should match rounding"
+        );
+    }
+}
+
+#[test]
+fn render_stats() {
+    let mut renderer = Bintje::new(256, 16);
+    renderer.fill_shape(
+        kurbo::Rect::new(2., 2., 200., 14.).to_path(0.1),
+        color::palette::css::ORANGE_RED,
+    );
+
+    let stats = renderer.stats();
+    assert_eq!(stats.lines, 4);
+    assert!(stats.tiles > 0 && stats.strips > 0);
+    assert_eq!(
+        stats.alpha_mask_bytes,
+        renderer.commands().alpha_masks.len(),
+        "all alpha masks were generated by this draw"
+    );
+    assert_eq!(
+        stats.wide_tile_commands.len(),
+        renderer.commands().wide_tiles.len()
+    );
+    for (counts, wide_tile) in stats
+        .wide_tile_commands
+        .iter()
+        .zip(renderer.commands().wide_tiles)
+    {
+        assert_eq!(
+            counts.total(),
+            wide_tile.commands.len(),
+            "every generated command should be counted"
+        );
+    }
+    assert_eq!(
+        stats.commands().total(),
+        renderer
+            .commands()
+            .wide_tiles
+            .iter()
+            .map(|wide_tile| wide_tile.commands.len())
+            .sum::<usize>()
+    );
+
+    let stats_len = stats.wide_tile_commands.len();
+    renderer.clear();
+    assert_eq!(
+        renderer.stats(),
+        &crate::RenderStats {
+            wide_tile_commands: vec![Default::default(); stats_len],
+            ..Default::default()
+        },
+        "clearing the scene should reset the statistics"
+    );
+}
+
+#[test]
+fn fallible_api() {
+    use crate::{wide_tile::try_cpu_rasterize, Error};
+
+    let mut renderer = Bintje::new(64, 64);
+    assert_eq!(
+        renderer.try_set_tolerance(0.),
+        Err(Error::InvalidTolerance(0.))
+    );
+    assert_eq!(renderer.tolerance(), Bintje::DEFAULT_TOLERANCE);
+    assert!(matches!(
+        renderer.try_fill_shape_with_tolerance(
+            kurbo::Circle::new((32., 32.), 20.),
+            color::palette::css::ORANGE_RED,
+            f64::NAN,
+        ),
+        Err(Error::InvalidTolerance(_))
+    ));
+    assert_eq!(
+        renderer.try_stroke(
+            kurbo::Circle::new((32., 32.), 20.).to_path(0.1),
+            &kurbo::Stroke::new(2.),
+            color::palette::css::ORANGE_RED,
+        ),
+        Ok(())
+    );
+
+    let commands = renderer.commands();
+    let mut img = vec![PremulRgba8::zeroed(); 64 * 63];
+    assert_eq!(
+        try_cpu_rasterize(64, 64, &mut img, commands.alpha_masks, commands.wide_tiles),
+        Err(Error::ImageSizeMismatch {
+            expected: 64 * 64,
+            actual: 64 * 63
+        })
+    );
+    let mut img = vec![PremulRgba8::zeroed(); 64 * 64];
+    assert!(matches!(
+        try_cpu_rasterize(
+            64,
+            64,
+            &mut img,
+            commands.alpha_masks,
+            &commands.wide_tiles[1..]
+        ),
+        Err(Error::WideTileCountMismatch { .. })
+    ));
+}
+
+#[test]
+fn hit_test() {
+    use crate::Hit;
+
+    let mut renderer = Bintje::new(256, 64);
+    renderer.set_draw_id(1);
+    renderer.fill_rect(
+        kurbo::Rect::new(10., 10., 200., 50.),
+        color::palette::css::BLUE,
+    );
+    renderer.set_draw_id(2);
+    renderer.fill_shape(
+        kurbo::Circle::new((150., 30.), 20.),
+        color::palette::css::ORANGE_RED,
+    );
+
+    let hits = |renderer: &Bintje, x: f64, y: f64| {
+        renderer
+            .hit_test(kurbo::Point::new(x, y))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(hits(&renderer, 5., 5.), []);
+    assert_eq!(
+        hits(&renderer, 20.5, 30.5),
+        [Hit {
+            draw_id: 1,
+            coverage: 255
+        }]
+    );
+    assert_eq!(
+        hits(&renderer, 150.5, 30.5),
+        [
+            Hit {
+                draw_id: 2,
+                coverage: 255
+            },
+            Hit {
+                draw_id: 1,
+                coverage: 255
+            }
+        ],
+        "hits should be reported topmost first"
+    );
+    // The rectangle's top edge half-covers this row of pixels.
+    renderer.clear();
+    renderer.fill_rect(
+        kurbo::Rect::new(10., 10.5, 200., 50.),
+        color::palette::css::BLUE,
+    );
+    assert_eq!(
+        hits(&renderer, 100., 10.),
+        [Hit {
+            draw_id: 0,
+            coverage: 128
+        }]
+    );
+    // Anti-aliased pixels are covered partially.
+    renderer.fill_shape(
+        kurbo::Circle::new((230., 30.), 20.),
+        color::palette::css::ORANGE_RED,
+    );
+    let edge = hits(&renderer, 210.5, 30.5);
+    assert!(
+        edge.len() == 1 && edge[0].coverage > 0 && edge[0].coverage < 255,
+        "the circle's edge should be partially covered: {edge:?}"
+    );
+    assert_eq!(hits(&renderer, 256., 30.), []);
+    assert_eq!(hits(&renderer, -0.5, 30.), []);
+}
+
+/// A small test font with the glyphs .notdef, space, I, O and V (glyph IDs 0 to 4).
+#[cfg(feature = "glyphs")]
+const TEST_FONT: &[u8] = include_bytes!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/../examples/assets/test_font/BintjeTest-Regular.ttf"
+));
+
+#[test]
+#[cfg(feature = "glyphs")]
+fn draw_glyphs() {
+    use crate::Glyph;
+
+    let mut env = testenv!();
+    env.set_size(128, 64);
+
+    let renderer = env.renderer();
+    let glyphs =
+        [(2, 8.), (3, 24.), (4, 56.), (1, 86.), (0, 98.)].map(|(id, x)| Glyph { id, x, y: 44. });
+    renderer.draw_glyphs(TEST_FONT, 40., glyphs, color::palette::css::ORANGE_RED);
+    // Glyphs that are not in the font are skipped.
+    renderer.draw_glyphs(
+        TEST_FONT,
+        40.,
+        [Glyph {
+            id: 1000,
+            x: 0.,
+            y: 0.,
+        }],
+        color::palette::css::ORANGE_RED,
+    );
+    assert_eq!(renderer.diagnostics(), []);
+
+    // The counter of the O is not filled.
+    let hits = |renderer: &Bintje, x: f64, y: f64| renderer.hit_test((x, y).into()).count();
+    assert_eq!(hits(renderer, 38., 30.), 0);
+    assert_eq!(hits(renderer, 26., 30.), 1);
+
+    env.rasterize_to_png();
+}
+
+#[test]
+#[cfg(feature = "glyphs")]
+fn draw_glyphs_invalid_font() {
+    use crate::{Error, Glyph};
+
+    let mut renderer = Bintje::new(64, 64);
+    let glyph = Glyph {
+        id: 2,
+        x: 10.,
+        y: 50.,
+    };
+    assert_eq!(
+        renderer.try_draw_glyphs(&TEST_FONT[..40], 40., [glyph], color::palette::css::BLUE),
+        Err(Error::InvalidFont)
+    );
+    // Invalid fonts should not generate commands.
+    assert_no_commands(&renderer);
+}
+
+#[test]
+fn prepared_path() {
+    let shape = kurbo::Circle::new((20.3, 15.6), 12.2).to_path(0.1);
+    let transform = kurbo::Affine::rotate(0.3).then_scale_non_uniform(1.5, 1.);
+    let mut renderer = Bintje::new(100, 70);
+    renderer.push_transform(transform);
+    let mut prepared = renderer.prepare_path(&shape);
+    renderer.pop_transform();
+    // Offsets within the viewport, both on and off the tile grid, and partially outside the
+    // viewport at all edges.
+    let offsets = [
+        (0, 0),
+        (32, 16),
+        (33, 19),
+        (37, 23),
+        (-15, 10),
+        (80, 3),
+        (25, -14),
+        (40, 55),
+        (-30, -20),
+        (200, 0),
+    ];
+    for offset in offsets {
+        renderer.clear();
+        renderer.fill_prepared_path(&mut prepared, offset, color::palette::css::ORANGE_RED);
+        let from_prepared = rasterize(&renderer);
+        renderer.clear();
+        renderer.push_transform(
+            kurbo::Affine::translate((offset.0.into(), offset.1.into())) * transform,
+        );
+        renderer.fill_shape(&shape, color::palette::css::ORANGE_RED);
+        let from_shape = rasterize(&renderer);
+
+        for (prepared, shape) in from_prepared.iter().zip(&from_shape) {
+            for (prepared, shape) in prepared.to_u8_array().into_iter().zip(shape.to_u8_array()) {
+                assert!(
+                    prepared.abs_diff(shape) <= 1,
+                    "prepared path should match path rendering at offset {offset:?}"
+                );
+            }
+        }
+    }
+    // Strips are only generated once per offset within a tile.
+    let mut phases: Vec<_> = offsets
+        .iter()
+        .map(|(x, y)| {
+            (
+                x.rem_euclid(crate::Tile::WIDTH.into()),
+                y.rem_euclid(crate::Tile::HEIGHT.into()),
+            )
+        })
+        .collect();
+    phases.sort_unstable();
+    phases.dedup();
+    assert_eq!(prepared.phases.len(), phases.len());
+
+    // The lines of a path that is too large to prepare are not left behind.
+    assert!(matches!(
+        renderer.try_prepare_path(kurbo::Rect::new(0., 0., 1e5, 10.)),
+        Err(crate::Error::PathTooLarge)
+    ));
+    assert!(renderer.lines.is_empty());
+}
+
+#[test]
+fn damage() {
+    /// Draw a static circle, and a small rectangle at `x`.
+    fn draw(renderer: &mut Bintje, x: f64) {
+        renderer.clear();
+        renderer.fill_shape(
+            kurbo::Circle::new((30., 30.), 20.3),
+            color::palette::css::SEA_GREEN,
+        );
+        renderer.fill_shape(
+            kurbo::Rect::new(x, 50.5, x + 10., 60.5),
+            color::palette::css::ORANGE_RED,
+        );
+    }
+
+    let mut renderer = Bintje::new(300, 70);
+    let mut img = vec![PremulRgba8::zeroed(); 300 * 70];
+
+    // All wide tiles are dirty in the first frame.
+    draw(&mut renderer, 10.);
+    let damage = renderer.damage();
+    assert!(damage.wide_tiles().iter().all(|dirty| *dirty));
+    assert_eq!(damage.rect(), Some(kurbo::Rect::new(0., 0., 300., 70.)));
+    let commands = renderer.commands();
+    wide_tile::cpu_rasterize_damaged(
+        300,
+        70,
+        &mut img,
+        commands.alpha_masks,
+        commands.wide_tiles,
+        &damage,
+    );
+
+    // Redrawing the same scene causes no damage.
+    draw(&mut renderer, 10.);
+    let damage = renderer.damage();
+    assert!(damage.is_empty());
+    assert_eq!(damage.rect(), None);
+
+    // Moving the rectangle to another wide tile dirties the wide tiles it was and is in.
+    draw(&mut renderer, 270.);
+    let damage = renderer.damage();
+    let tile_height = f64::from(crate::Tile::HEIGHT);
+    let dirty_rect = kurbo::Rect::new(
+        0.,
+        (50.5 / tile_height).floor() * tile_height,
+        300.,
+        ((60.5 / tile_height).ceil() * tile_height).min(70.),
+    );
+    assert_eq!(damage.rect(), Some(dirty_rect));
+    let commands = renderer.commands();
+    wide_tile::cpu_rasterize_damaged(
+        300,
+        70,
+        &mut img,
+        commands.alpha_masks,
+        commands.wide_tiles,
+        &damage,
+    );
+    assert!(
+        img == rasterize(&renderer),
+        "rasterizing the damage should match rasterizing the full frame"
+    );
+
+    renderer.reset_damage();
+    assert!(renderer.damage().wide_tiles().iter().all(|dirty| *dirty));
+}
+
+#[test]
+fn compact_alpha_masks() {
+    let mut renderer = Bintje::new(256, 64);
+    // Identical shapes at offsets that are multiples of the tile size have identical alpha masks.
+    for x in 0..6 {
+        renderer.fill_shape(
+            kurbo::Circle::new((20. + f64::from(x) * 40., 30.), 15.3),
+            color::palette::css::ORANGE_RED,
+        );
+    }
+    renderer.fill_shape(
+        kurbo::Rect::new(1.5, 50.5, 250.5, 60.5),
+        color::palette::css::SEA_GREEN,
+    );
+    let expected = rasterize(&renderer);
+    let alpha_mask_bytes = renderer.commands().alpha_masks.len();
+    renderer.compact_alpha_masks();
+    let compacted_bytes = renderer.commands().alpha_masks.len();
+    assert!(
+        compacted_bytes * 4 < alpha_mask_bytes,
+        "compacting should deduplicate alpha masks, got {compacted_bytes} of {alpha_mask_bytes} bytes"
+    );
+    assert!(
+        rasterize(&renderer) == expected,
+        "compacting alpha masks should not change the rendering"
+    );
+
+    // Compacting again finds nothing to deduplicate.
+    renderer.compact_alpha_masks();
+    assert_eq!(renderer.commands().alpha_masks.len(), compacted_bytes);
+}
+
+#[test]
+fn fill_mask() {
+    const STRIDE: usize = 103;
+    let circle = kurbo::Circle::new((40., 30.), 22.3);
+    let triangle = kurbo::BezPath::from_vec(vec![
+        kurbo::PathEl::MoveTo((30., 10.).into()),
+        kurbo::PathEl::LineTo((95., 35.).into()),
+        kurbo::PathEl::LineTo((20., 65.).into()),
+        kurbo::PathEl::ClosePath,
+    ]);
+
+    let mut renderer = Bintje::new(100, 70);
+    // Padding between rows is left untouched.
+    let mut mask = vec![7; STRIDE * 70];
+    for row in mask.chunks_exact_mut(STRIDE) {
+        row[..100].fill(0);
+    }
+    renderer.fill_mask(circle, &mut mask, STRIDE);
+    renderer.fill_mask(&triangle, &mut mask, STRIDE);
+    assert!(mask.chunks_exact(STRIDE).all(|row| row[100..] == [7; 3]));
+    assert_no_commands(&renderer);
+
+    // The mask matches the alpha of the shapes composited over each other.
+    renderer.fill_shape(circle, color::palette::css::BLACK);
+    renderer.fill_shape(&triangle, color::palette::css::BLACK);
+    let img = rasterize(&renderer);
+    for (row, img_row) in mask.chunks_exact(STRIDE).zip(img.chunks_exact(100)) {
+        for (coverage, pixel) in row.iter().zip(img_row) {
+            assert!(coverage.abs_diff(pixel.a) <= 1);
+        }
+    }
+
+    assert_eq!(
+        renderer.try_fill_mask(circle, &mut mask[..STRIDE * 69], STRIDE),
+        Err(crate::Error::MaskTooSmall {
+            stride: STRIDE,
+            len: STRIDE * 69
+        })
+    );
+    assert!(matches!(
+        renderer.try_fill_mask(circle, &mut mask, 99),
+        Err(crate::Error::MaskTooSmall { .. })
+    ));
+}
+
+#[test]
+fn render_bands() {
+    fn draw(renderer: &mut Bintje) {
+        renderer.fill_shape(
+            kurbo::Circle::new((40., 30.), 22.3),
+            color::palette::css::SEA_GREEN,
+        );
+        renderer.stroke(
+            kurbo::Line::new((5., 60.), (95., 3.)).path_elements(0.1),
+            &kurbo::Stroke::new(3.),
+            color::palette::css::ORANGE_RED,
+        );
+        renderer.fill_rect(
+            kurbo::Rect::new(10.5, 40.2, 80.7, 66.9),
+            color::palette::css::ROYAL_BLUE.with_alpha(0.5),
+        );
+    }
+
+    // The height is not a multiple of the tile height, such that the last band is shorter.
+    let (width, height) = (100, 4 * crate::Tile::HEIGHT + 3);
+    let mut renderer = Bintje::new(width, height);
+    draw(&mut renderer);
+    let expected = rasterize(&renderer);
+
+    let mut img = Vec::new();
+    crate::render_bands(
+        width,
+        height,
+        |renderer, _| draw(renderer),
+        |y, band| {
+            assert_eq!(img.len(), usize::from(y) * usize::from(width));
+            img.extend_from_slice(band);
+        },
+    );
+    assert_eq!(img.len(), expected.len());
+    for (banded, expected) in img.iter().zip(&expected) {
+        for (banded, expected) in banded.to_u8_array().into_iter().zip(expected.to_u8_array()) {
+            assert!(
+                banded.abs_diff(expected) <= 1,
+                "rendering in bands should match rendering the full frame"
+            );
+        }
+    }
+
+    // Writing the bands streams the unpremultiplied rows.
+    let mut bytes = Vec::new();
+    crate::write_bands(width, height, |renderer, _| draw(renderer), &mut bytes).unwrap();
+    let unpremultiplied: Vec<u8> = img
+        .iter()
+        .flat_map(|pixel| {
+            color::PremulColor::<color::Srgb>::from(*pixel)
+                .un_premultiply()
+                .to_rgba8()
+                .to_u8_array()
+        })
+        .collect();
+    assert!(bytes == unpremultiplied);
+
+    // Rendering stops at the first write error.
+    let mut bands = 0;
+    let result = crate::write_bands(
+        width,
+        height,
+        |_, _| bands += 1,
+        std::io::Cursor::new(&mut [0; 4][..]),
+    );
+    assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::WriteZero);
+    assert_eq!(bands, 1);
+}
+
+#[test]
+fn path_strips() {
+    let shape = kurbo::Circle::new((40., 30.), 22.3);
+    let mut renderer = Bintje::new(100, 70);
+    renderer.push_transform(kurbo::Affine::scale_non_uniform(1.5, 1.));
+
+    // Reconstructing the coverage from the strips matches rendering a coverage mask.
+    let mut mask = vec![0; 100 * 70];
+    for strip in renderer.path_strips(shape) {
+        assert_eq!(strip.x % u32::from(crate::Tile::WIDTH), 0);
+        assert_eq!(strip.y % u32::from(crate::Tile::HEIGHT), 0);
+        for (y, row) in (strip.y..strip.y + u32::from(crate::Tile::HEIGHT)).enumerate() {
+            if row >= 70 {
+                break;
+            }
+            let row = &mut mask[row as usize * 100..][..100];
+            for x in strip.fill_x..strip.x.min(100) {
+                row[x as usize] = strip.pixel_coverage[y];
+            }
+            for x in strip.x..(strip.x + strip.width).min(100) {
+                row[x as usize] =
+                    strip.alpha_mask[(x - strip.x) as usize * crate::Tile::HEIGHT as usize + y];
+            }
+        }
+    }
+    let mut expected = vec![0; 100 * 70];
+    renderer.fill_mask(shape, &mut expected, 100);
+    assert!(mask == expected);
+    assert!(mask.iter().any(|coverage| *coverage == 255));
+
+    // Strips do not generate draw commands.
+    assert_no_commands(&renderer);
+    assert_eq!(
+        renderer
+            .path_strips(kurbo::Circle::new((-40., 30.), 20.))
+            .count(),
+        0
+    );
+}
+
+#[test]
+fn fine_rasterizer() {
+    crate::testing::check_rasterizer(&mut crate::CpuRasterizer, 0);
+}
+
+#[test]
+fn validate_commands() {
+    use crate::{validate_commands, Command, CommandError, Commands, Sample, SparseFill, WideTile};
+
+    let mut renderer = Bintje::new(300, 70);
+    renderer.fill_shape(
+        kurbo::Circle::new((30., 30.), 20.3),
+        color::palette::css::SEA_GREEN,
+    );
+    assert_eq!(validate_commands(300, 70, renderer.commands()), []);
+    assert_eq!(
+        validate_commands(200, 70, renderer.commands()),
+        [CommandError::WideTileCountMismatch {
+            expected: 200_u16.div_ceil(WideTile::WIDTH_PX) as usize
+                * 70_u16.div_ceil(crate::Tile::HEIGHT) as usize,
+            actual: renderer.commands().wide_tiles.len(),
+        }]
+    );
+
+    let color = PremulRgba8::from_u32(0xffffffff);
+    let wide_tiles = [
+        WideTile {
+            commands: vec![
+                Command::Sample(Sample {
+                    x: 0,
+                    width: 2,
+                    color,
+                    alpha_idx: 0,
+                    draw_id: 0,
+                }),
+                Command::SparseFill(SparseFill {
+                    x: WideTile::WIDTH_TILES - 1,
+                    width: 2,
+                    color,
+                    draw_id: 0,
+                }),
+                Command::PopClip(()),
+            ],
+        },
+        WideTile {
+            commands: vec![
+                Command::PushClip(()),
+                Command::PushClip(()),
+                Command::PopClip(()),
+            ],
+        },
+    ];
+    let alpha_masks = [0; crate::Tile::WIDTH as usize * crate::Tile::HEIGHT as usize];
+    let commands = Commands {
+        wide_tiles: &wide_tiles,
+        alpha_masks: &alpha_masks,
+    };
+    let errors = validate_commands(WideTile::WIDTH_PX * 2, crate::Tile::HEIGHT, commands);
+    assert_eq!(
+        errors,
+        [
+            CommandError::AlphaMaskOutOfBounds {
+                wide_tile: 0,
+                command: 0,
+                end: alpha_masks.len() * 2,
+                len: alpha_masks.len(),
+            },
+            CommandError::OutsideWideTile {
+                wide_tile: 0,
+                command: 1,
+                x: WideTile::WIDTH_TILES - 1,
+                width: 2,
+            },
+            CommandError::UnmatchedPopClip {
+                wide_tile: 0,
+                command: 2,
+            },
+            CommandError::UnmatchedPushClip { wide_tile: 1 },
+        ]
+    );
+    assert_eq!(
+        crate::check_commands(WideTile::WIDTH_PX * 2, crate::Tile::HEIGHT, commands),
+        Err(errors[0])
+    );
+    assert_eq!(crate::check_commands(300, 70, renderer.commands()), Ok(()));
+
+    // Malformed commands are rejected before rasterizing by the fallible rasterizers, also in
+    // release builds.
+    let mut img =
+        vec![PremulRgba8::zeroed(); WideTile::WIDTH_PX as usize * 2 * crate::Tile::HEIGHT as usize];
+    assert_eq!(
+        crate::try_cpu_rasterize(
+            WideTile::WIDTH_PX * 2,
+            crate::Tile::HEIGHT,
+            &mut img,
+            &alpha_masks,
+            &wide_tiles,
+        ),
+        Err(crate::Error::InvalidCommands(errors[0]))
+    );
+    let damage = Bintje::new(WideTile::WIDTH_PX * 2, crate::Tile::HEIGHT).damage();
+    assert_eq!(
+        crate::try_cpu_rasterize_damaged(
+            WideTile::WIDTH_PX * 2,
+            crate::Tile::HEIGHT,
+            &mut img,
+            &alpha_masks,
+            &wide_tiles,
+            &damage,
+        ),
+        Err(crate::Error::InvalidCommands(errors[0]))
+    );
+}
+
+#[test]
+fn debug_rasterizer() {
+    use crate::{DebugRasterizer, FineRasterizer, RenderTarget, Tile, WideTile};
+
+    let mut renderer = Bintje::new(300, 70);
+    renderer.fill_shape(
+        kurbo::Circle::new((30., 30.), 20.3),
+        color::palette::css::SEA_GREEN,
+    );
+    let rasterize = |mut rasterizer: DebugRasterizer| {
+        let mut img = vec![PremulRgba8::zeroed(); 300 * 70];
+        rasterizer
+            .rasterize(
+                renderer.commands(),
+                RenderTarget {
+                    width: 300,
+                    height: 70,
+                    pixels: &mut img,
+                    damage: None,
+                },
+            )
+            .unwrap();
+        assert!(img.iter().all(|pixel| pixel.a == 255));
+        move |x: u16, y: u16| img[usize::from(y) * 300 + usize::from(x)]
+    };
+    let none = DebugRasterizer {
+        heatmap: false,
+        commands: false,
+        samples: false,
+        wide_tiles: false,
+    };
+
+    // Without overlays, the scene is faded over white.
+    let pixel = rasterize(none);
+    assert_eq!(
+        pixel(100, 61).to_u32(),
+        PremulRgba8::from_u32(u32::MAX).to_u32()
+    );
+    let center = pixel(30, 30);
+    assert!(center.g > center.r && center.g < 255);
+
+    // Wide tile boundaries are drawn at the top-left of every wide tile.
+    let pixel = rasterize(DebugRasterizer {
+        wide_tiles: true,
+        ..none
+    });
+    let boundary = pixel(WideTile::WIDTH_PX.min(299), Tile::HEIGHT + 1);
+    assert!(boundary.r == boundary.g && boundary.r < 128);
+    assert_eq!(
+        pixel(100, 61).to_u32(),
+        PremulRgba8::from_u32(u32::MAX).to_u32()
+    );
+
+    // The busiest wide tiles are tinted red, and empty wide tiles blue.
+    let pixel = rasterize(DebugRasterizer {
+        heatmap: true,
+        ..none
+    });
+    let empty = pixel(100, 61);
+    assert!(empty.b > empty.r);
+    let busy = pixel(100, 30);
+    assert!(busy.r > busy.b);
+
+    // Alpha-sampled areas are outlined, and commands tinted.
+    let plain = rasterize(none);
+    let pixel = rasterize(DebugRasterizer {
+        samples: true,
+        commands: true,
+        ..none
+    });
+    assert_ne!(pixel(30, 10).to_u32(), plain(30, 10).to_u32());
+    assert_ne!(pixel(30, 30).to_u32(), plain(30, 30).to_u32());
+
+    // Zero-width commands, e.g., at the left edge of a wide tile, are not outlined.
+    let wide_tiles = [WideTile {
+        commands: vec![crate::Command::Sample(crate::Sample {
+            x: 0,
+            width: 0,
+            color: PremulRgba8::from_u32(u32::MAX),
+            alpha_idx: 0,
+            draw_id: 0,
+        })],
+    }];
+    let mut img = vec![PremulRgba8::zeroed(); usize::from(Tile::HEIGHT)];
+    DebugRasterizer::default()
+        .rasterize(
+            crate::Commands {
+                wide_tiles: &wide_tiles,
+                alpha_masks: &[],
+            },
+            RenderTarget {
+                width: 1,
+                height: Tile::HEIGHT,
+                pixels: &mut img,
+                damage: None,
+            },
+        )
+        .unwrap();
+}
@@ -10,12 +10,19 @@ pub struct Tile {
 
 impl Tile {
     /// Tile width in pixels.
-    pub const WIDTH: u16 = 4;
+    ///
+    /// This is 4, or 8 with the `tile-8x8` feature.
+    pub const WIDTH: u16 = TILE_SIZE;
 
     /// Tile height in pixels.
-    pub const HEIGHT: u16 = 4;
+    ///
+    /// This is 4, or 8 with the `tile-8x8` feature.
+    pub const HEIGHT: u16 = TILE_SIZE;
 }
 
+/// Tile width and height in pixels.
+const TILE_SIZE: u16 = if cfg!(feature = "tile-8x8") { 8 } else { 4 };
+
 impl std::cmp::PartialEq for Tile {
     fn eq(&self, other: &Self) -> bool {
         self.x == other.x
@@ -50,6 +57,9 @@ pub(crate) struct TileRow {
     /// The per-pixel area coverage of the path at this tile row before the start of the row (i.e.,
     /// the pixel coverage of the path segments to the left of the viewport).
     pub area_coverage: [f32; Tile::HEIGHT as usize],
+    /// The number of tiles counted for this row during tile generation, used to allocate `tiles`
+    /// up front.
+    tile_count: usize,
 }
 
 impl TileRow {
@@ -58,132 +68,377 @@ impl TileRow {
             tiles: Vec::with_capacity(64),
             winding: 0,
             area_coverage: [0.; Tile::HEIGHT as usize],
+            tile_count: 0,
         }
     }
 
-    pub(crate) fn sort(&mut self) {
-        self.tiles.sort_unstable();
+    /// Sort the tiles by their x-coordinate, using `sorter`'s scratch buffers.
+    pub(crate) fn sort(&mut self, sorter: &mut TileSorter) {
+        sorter.sort(&mut self.tiles);
     }
 
     pub(crate) fn clear(&mut self) {
         self.tiles.clear();
         self.winding = 0;
         self.area_coverage = [0.; Tile::HEIGHT as usize];
+        self.tile_count = 0;
     }
 }
 
-pub(crate) fn generate_tiles(rows: &mut [TileRow], width: u16, lines: &[Line]) {
-    for (line_idx, line) in lines.iter().copied().enumerate() {
-        if line.p0.y == line.p1.y {
-            // Skip horizontal geometry.
+/// Sorts tiles by their x-coordinate.
+///
+/// Tile x-coordinates are bounded by the viewport width, so this uses a counting sort over the
+/// range of tile columns within a row rather than a comparison sort. The sort is stable: tiles in
+/// the same column remain in line order.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct TileSorter {
+    /// Per tile column, the offset of the next tile in that column in `sorted`.
+    offsets: Vec<u32>,
+    /// The sorted tiles. This is swapped with the tiles being sorted.
+    sorted: Vec<Tile>,
+}
+
+impl TileSorter {
+    pub(crate) fn sort(&mut self, tiles: &mut Vec<Tile>) {
+        if tiles.len() < 2 {
+            return;
+        }
+
+        let (min_x, max_x) = tiles.iter().fold((u16::MAX, 0), |(min_x, max_x), tile| {
+            (min_x.min(tile.x), max_x.max(tile.x))
+        });
+        if min_x == max_x {
+            return;
+        }
+
+        // Count the tiles per column, offset by one, and turn the counts into the offset of the
+        // first tile in each column.
+        self.offsets.clear();
+        self.offsets.resize((max_x - min_x) as usize + 2, 0);
+        for tile in tiles.iter() {
+            self.offsets[(tile.x - min_x) as usize + 1] += 1;
+        }
+        let mut offset = 0;
+        for count in self.offsets.iter_mut() {
+            offset += *count;
+            *count = offset;
+        }
+
+        self.sorted.clear();
+        self.sorted.resize(tiles.len(), Tile { x: 0, line_idx: 0 });
+        for tile in tiles.iter() {
+            let offset = &mut self.offsets[(tile.x - min_x) as usize];
+            self.sorted[*offset as usize] = *tile;
+            *offset += 1;
+        }
+
+        std::mem::swap(tiles, &mut self.sorted);
+    }
+}
+
+/// Generate the tiles for the given lines, binning them into `rows`.
+///
+/// Geometry above, below or to the right of the viewport is culled, as it cannot contribute to
+/// coverage within the viewport. Geometry to the left of the viewport is accounted for by the
+/// rows' winding and area coverage.
+///
+/// Exactly the tiles a line passes through are generated. This happens in two passes: the first
+/// walks the lines, recording the runs of tiles they pass through in `spans` and counting the
+/// tiles per row, such that the rows can be allocated once. The second generates the tiles from
+/// the runs.
+///
+/// Keeping the walk separate from pushing the tiles is faster than pushing them while walking: on
+/// the tiger rendered at 1000 by 1000 pixels, tile generation takes about 0.54ms per frame in two
+/// passes, against 0.69ms in one. `spans` is scratch reused across draws, so it stops allocating
+/// once it has grown to fit the largest path.
+///
+/// The number of `lines` must fit in `u32`.
+pub(crate) fn generate_tiles(
+    rows: &mut [TileRow],
+    spans: &mut Vec<TileSpan>,
+    width: u16,
+    lines: &[Line],
+) {
+    spans.clear();
+    if rows.is_empty() || width == 0 {
+        return;
+    }
+
+    let viewport = Viewport {
+        right_x: width as f32 / Tile::WIDTH as f32,
+        bottom_y: rows.len() as f32,
+        width_in_tiles: width.div_ceil(Tile::WIDTH),
+    };
+
+    for (line_idx, line) in lines.iter().enumerate() {
+        let Some(line) = TileLine::new(line, &viewport) else {
             continue;
+        };
+
+        if line.left_x < 0. {
+            accumulate_left_coverage(rows, &line);
         }
 
-        let line_idx = u32::try_from(line_idx).expect("Number of lines per path overflowed");
+        #[expect(
+            clippy::cast_possible_truncation,
+            reason = "the number of lines is checked to fit in `u32` before tiling"
+        )]
+        let line_idx = line_idx as u32;
+        line.for_each_span(&viewport, |y, x_start, x_end| {
+            rows[y as usize].tile_count += (x_end - x_start) as usize + 1;
+            spans.push(TileSpan {
+                y,
+                x_start,
+                x_end,
+                line_idx,
+            });
+        });
+    }
+
+    for row in rows.iter_mut() {
+        row.tiles.reserve(row.tile_count);
+    }
+
+    for span in spans.iter() {
+        let row = &mut rows[span.y as usize];
+        for x in span.x_start..span.x_end + 1 {
+            row.tiles.push(Tile {
+                x,
+                line_idx: span.line_idx,
+            });
+        }
+    }
+}
+
+/// A horizontal run of tiles within a single row that a line passes through.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct TileSpan {
+    /// The tile row.
+    y: u16,
+    /// The first tile column of the run.
+    x_start: u16,
+    /// The last tile column of the run (inclusive).
+    x_end: u16,
+    /// The index of the line into the line buffer.
+    line_idx: u32,
+}
+
+/// The viewport's extents in tile coordinates.
+struct Viewport {
+    /// The viewport's right edge.
+    right_x: f32,
+    /// The viewport's bottom edge. This is the number of tile rows.
+    bottom_y: f32,
+    /// The number of tile columns.
+    width_in_tiles: u16,
+}
+
+/// A line in tile coordinates that may cover part of the viewport.
+struct TileLine {
+    p0_x: f32,
+    p0_y: f32,
+    left_x: f32,
+    left_y: f32,
+    right_x: f32,
+    right_y: f32,
+    top_y: f32,
+    bottom_y: f32,
+    /// The winding direction of the line: `1.` if the line is directed upwards, `-1.` if it is
+    /// directed downwards.
+    sign: f32,
+    /// The change in x per unit of y. This is finite.
+    x_slope: f32,
+}
 
-        let width_in_tiles = width.div_ceil(Tile::WIDTH);
+impl TileLine {
+    /// Convert the line to tile coordinates. Returns `None` if the line is horizontal or lies
+    /// entirely above, below or to the right of the viewport.
+    fn new(line: &Line, viewport: &Viewport) -> Option<Self> {
+        if line.p0.y == line.p1.y {
+            // Skip horizontal geometry.
+            return None;
+        }
 
         let p0_x = line.p0.x / Tile::WIDTH as f32;
         let p0_y = line.p0.y / Tile::HEIGHT as f32;
         let p1_x = line.p1.x / Tile::WIDTH as f32;
         let p1_y = line.p1.y / Tile::HEIGHT as f32;
 
-        let sign = (p0_y - p1_y).signum();
-
-        let (line_left_x, line_left_y, line_right_x, line_right_y) = if p0_x < p1_x {
+        let (left_x, left_y, right_x, right_y) = if p0_x < p1_x {
             (p0_x, p0_y, p1_x, p1_y)
         } else {
             (p1_x, p1_y, p0_x, p0_y)
         };
-        let (line_top_y, line_bottom_y) = if p0_y < p1_y {
+        let (top_y, bottom_y) = if p0_y < p1_y {
             (p0_y, p1_y)
         } else {
             (p1_y, p0_y)
         };
-        let y_top_tiles = (line_top_y as u16).min(rows.len().saturating_sub(1) as u16);
-        let y_bottom_tiles = (line_bottom_y as u16).min(rows.len().saturating_sub(1) as u16);
-
-        if line_left_x == line_right_x {
-            if line_left_x < 0. {
-                for y_idx in y_top_tiles..=y_bottom_tiles {
-                    let row_top_y = y_idx as f32;
-                    let row = &mut rows[y_idx as usize];
-                    row.winding +=
-                        sign as i32 * (line_top_y <= row_top_y && line_bottom_y > row_top_y) as i32;
-
-                    for y_px in 0..Tile::HEIGHT {
-                        let px_top_y = y_idx as f32 + y_px as f32 * (1. / Tile::HEIGHT as f32);
-                        let px_bottom_y = y_idx as f32
-                            + y_px as f32 * (1. / Tile::HEIGHT as f32)
-                            + (1. / Tile::HEIGHT as f32);
-                        row.area_coverage[y_px as usize] += Tile::HEIGHT as f32
-                            * sign
-                            * (line_bottom_y.min(px_bottom_y) - line_top_y.max(px_top_y)).max(0.);
-                    }
+
+        // Cull geometry that lies entirely above, below or to the right of the viewport.
+        if bottom_y <= 0. || top_y >= viewport.bottom_y || left_x >= viewport.right_x {
+            return None;
+        }
+
+        // The slope overflows for lines that are nearly, but not quite, horizontal. Such lines may
+        // still cross a row's top edge and contribute winding, so clamp the slope rather than
+        // dropping them. Where such a line spans more than one row within the viewport, its
+        // vertical extent is at least the float spacing at the row boundary, over which the
+        // clamped slope still carries it across the whole viewport; x-coordinates are clamped to
+        // the line's extents while walking the tiles.
+        let x_slope = ((p1_x - p0_x) / (p1_y - p0_y)).clamp(-f32::MAX, f32::MAX);
+
+        Some(Self {
+            p0_x,
+            p0_y,
+            left_x,
+            left_y,
+            right_x,
+            right_y,
+            top_y,
+            bottom_y,
+            sign: (p0_y - p1_y).signum(),
+            x_slope,
+        })
+    }
+
+    /// The change in y per unit of x. This is not finite for vertical lines.
+    ///
+    /// This is only needed for lines crossing the viewport's left or right edge, so it is
+    /// computed on demand.
+    fn y_slope(&self) -> f32 {
+        (self.right_y - self.left_y) / (self.right_x - self.left_x)
+    }
+
+    /// Walk the tiles the line passes through within the viewport, row by row. For every row,
+    /// `f` is called with the row index and the inclusive range of tile columns the line passes
+    /// through.
+    ///
+    /// A tile is passed through if the line intersects its half-open area: lines ending exactly on
+    /// a tile's top or left edge do not pass through the tile above or to the left of that edge.
+    #[inline(always)]
+    fn for_each_span(&self, viewport: &Viewport, mut f: impl FnMut(u16, u16, u16)) {
+        // Clip the line to the part within the viewport.
+        let (clip_top_y, clip_bottom_y) = if self.left_x >= 0. && self.right_x <= viewport.right_x {
+            (self.top_y, self.bottom_y)
+        } else {
+            let y_slope = self.y_slope();
+            if !y_slope.is_finite() {
+                // The line is vertical.
+                if self.left_x < 0. {
+                    return;
                 }
+                (self.top_y, self.bottom_y)
             } else {
-                for y_idx in y_top_tiles..=y_bottom_tiles {
-                    let x_idx = p0_x as u16;
-                    let row = &mut rows[y_idx as usize];
-                    row.tiles.push(Tile { x: x_idx, line_idx });
-                }
-            }
-        } else {
-            let x_slope = (p1_x - p0_x) / (p1_y - p0_y);
-            if !x_slope.is_finite() {
-                // Horizontal geometry is elided.
-                unreachable!()
+                // The y-coordinates at which the line crosses the viewport's left and right edges.
+                let left_edge_y = self.left_y - self.left_x * y_slope;
+                let right_edge_y = self.left_y + (viewport.right_x - self.left_x) * y_slope;
+                let (min_y, max_y) = if y_slope > 0. {
+                    (left_edge_y, right_edge_y)
+                } else {
+                    (right_edge_y, left_edge_y)
+                };
+                (self.top_y.max(min_y), self.bottom_y.min(max_y))
             }
+        };
+        let clip_top_y = clip_top_y.max(0.);
+        let clip_bottom_y = clip_bottom_y.min(viewport.bottom_y);
+        if clip_top_y >= clip_bottom_y {
+            return;
+        }
+        let clip_left_x = self.left_x.max(0.);
+        let clip_right_x = self.right_x.min(viewport.right_x);
 
-            let y_top_tiles = (line_top_y as u16).min(rows.len() as u16);
-            let y_bottom_tiles = (line_bottom_y as u16).min(rows.len().saturating_sub(1) as u16);
-
-            for y_idx in y_top_tiles..=y_bottom_tiles {
-                let row = &mut rows[y_idx as usize];
-                let row_top_y = y_idx as f32;
-
-                let ymin = line_top_y.max(row_top_y).min(row_top_y + 1.);
-                let ymax = line_bottom_y.max(row_top_y).min(row_top_y + 1.);
-
-                if line_left_x < 0. {
-                    let y_slope = (line_right_y - line_left_y) / (line_right_x - line_left_x);
-                    if !y_slope.is_finite() {
-                        // Prevented by the branch above.
-                        unreachable!()
-                    }
-
-                    // Line's y-coord at the left viewport edge.
-                    let viewport_y_left = (line_left_y - line_left_x * y_slope)
-                        .max(line_top_y)
-                        .min(line_bottom_y);
-                    row.winding += sign as i32
-                        * ((line_left_y - row_top_y).signum()
-                            != (viewport_y_left - row_top_y).signum())
-                            as i32;
-                    for y_px in 0..Tile::HEIGHT {
-                        let px_top_y = y_idx as f32 + y_px as f32 * (1. / Tile::HEIGHT as f32);
-                        let px_bottom_y = y_idx as f32
-                            + y_px as f32 * (1. / Tile::HEIGHT as f32)
-                            + (1. / Tile::HEIGHT as f32);
-                        row.area_coverage[y_px as usize] += Tile::HEIGHT as f32
-                            * sign
-                            * (viewport_y_left.min(px_bottom_y).max(px_top_y)
-                                - line_left_y.min(px_bottom_y).max(px_top_y))
-                            .abs();
-                    }
-                }
+        let last_column = viewport.width_in_tiles - 1;
+        let columns = |min_x: f32, max_x: f32| {
+            let x_start = (min_x as u16).min(last_column);
+            let x_end = ((max_x.ceil() as u16).saturating_sub(1)).clamp(x_start, last_column);
+            (x_start, x_end)
+        };
+
+        let y_top_tiles = clip_top_y as u16;
+        let y_bottom_tiles = (clip_bottom_y.ceil() as u16).saturating_sub(1);
 
-                // The line's x-coordinates at the line's top- and bottom-most points within the
-                // row.
-                let row_y_top_x = p0_x + (ymin - p0_y) * x_slope;
-                let row_y_bottom_x = p0_x + (ymax - p0_y) * x_slope;
+        if y_top_tiles >= y_bottom_tiles {
+            // The line lies within a single row.
+            let (x_start, x_end) = columns(clip_left_x, clip_right_x);
+            f(y_top_tiles, x_start, x_end);
+            return;
+        }
 
-                let row_left_x = f32::min(row_y_top_x, row_y_bottom_x).max(line_left_x);
-                let row_right_x = f32::max(row_y_top_x, row_y_bottom_x).min(line_right_x);
+        // The line's x-coordinate at `y`, clamped to guard against float error.
+        let x_at =
+            |y: f32| (self.p0_x + (y - self.p0_y) * self.x_slope).clamp(clip_left_x, clip_right_x);
 
-                for x_idx in row_left_x as u16..(row_right_x as u16 + 1).min(width_in_tiles) {
-                    row.tiles.push(Tile { x: x_idx, line_idx });
-                }
+        // The line's x-coordinate where it enters the current row. This is shared with the
+        // previous row's exit, so consecutive rows join up exactly.
+        let mut enter_x = x_at(clip_top_y);
+        for y_idx in y_top_tiles..=y_bottom_tiles {
+            let exit_x = x_at(clip_bottom_y.min(y_idx as f32 + 1.));
+            let (x_start, x_end) = if enter_x < exit_x {
+                columns(enter_x, exit_x)
+            } else {
+                columns(exit_x, enter_x)
+            };
+            enter_x = exit_x;
+            f(y_idx, x_start, x_end);
+        }
+    }
+}
+
+/// Accumulate the winding and area coverage of the part of the line left of the viewport into the
+/// rows.
+fn accumulate_left_coverage(rows: &mut [TileRow], line: &TileLine) {
+    let last_row = (rows.len() - 1) as u16;
+
+    // The rows the line touches, clipped to the viewport. A line ending exactly on a row's top edge
+    // does not touch that row.
+    let y_top_tiles = line.top_y.max(0.) as u16;
+    let y_bottom_tiles = ((line.bottom_y.ceil() - 1.) as u16).min(last_row);
+
+    let (line_top_y, line_bottom_y) = (line.top_y, line.bottom_y);
+    let sign = line.sign;
+
+    let y_slope = line.y_slope();
+    if !y_slope.is_finite() {
+        for y_idx in y_top_tiles..=y_bottom_tiles {
+            let row_top_y = y_idx as f32;
+            let row = &mut rows[y_idx as usize];
+            row.winding +=
+                sign as i32 * (line_top_y <= row_top_y && line_bottom_y > row_top_y) as i32;
+
+            for y_px in 0..Tile::HEIGHT {
+                let px_top_y = y_idx as f32 + y_px as f32 * (1. / Tile::HEIGHT as f32);
+                let px_bottom_y = y_idx as f32
+                    + y_px as f32 * (1. / Tile::HEIGHT as f32)
+                    + (1. / Tile::HEIGHT as f32);
+                row.area_coverage[y_px as usize] += Tile::HEIGHT as f32
+                    * sign
+                    * (line_bottom_y.min(px_bottom_y) - line_top_y.max(px_top_y)).max(0.);
+            }
+        }
+    } else {
+        // Line's y-coord at the left viewport edge.
+        let viewport_y_left = (line.left_y - line.left_x * y_slope)
+            .max(line_top_y)
+            .min(line_bottom_y);
+
+        for y_idx in y_top_tiles..=y_bottom_tiles {
+            let row_top_y = y_idx as f32;
+            let row = &mut rows[y_idx as usize];
+            row.winding += sign as i32
+                * ((line.left_y - row_top_y).signum() != (viewport_y_left - row_top_y).signum())
+                    as i32;
+            for y_px in 0..Tile::HEIGHT {
+                let px_top_y = y_idx as f32 + y_px as f32 * (1. / Tile::HEIGHT as f32);
+                let px_bottom_y = y_idx as f32
+                    + y_px as f32 * (1. / Tile::HEIGHT as f32)
+                    + (1. / Tile::HEIGHT as f32);
+                row.area_coverage[y_px as usize] += Tile::HEIGHT as f32
+                    * sign
+                    * (viewport_y_left.min(px_bottom_y).max(px_top_y)
+                        - line.left_y.min(px_bottom_y).max(px_top_y))
+                    .abs();
             }
         }
     }
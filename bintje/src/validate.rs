@@ -0,0 +1,211 @@
+use core::{fmt, ops::ControlFlow};
+
+use crate::{wide_tile::WIDE_TILE_WIDTH_PX, Command, Commands, Tile, WideTile};
+
+/// A problem found in draw commands by [`validate_commands`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CommandError {
+    /// The number of wide tiles does not match the size of the render target.
+    WideTileCountMismatch {
+        /// The number of wide tiles covering the render target.
+        expected: usize,
+        /// The number of wide tiles given.
+        actual: usize,
+    },
+    /// A command extends beyond the right edge of its wide tile.
+    OutsideWideTile {
+        /// The index of the wide tile.
+        wide_tile: usize,
+        /// The index of the command within the wide tile.
+        command: usize,
+        /// The offset of the command within the wide tile, in tiles.
+        x: u16,
+        /// The width of the command, in tiles.
+        width: u16,
+    },
+    /// A sample command's alpha mask extends beyond the end of the alpha masks.
+    AlphaMaskOutOfBounds {
+        /// The index of the wide tile.
+        wide_tile: usize,
+        /// The index of the command within the wide tile.
+        command: usize,
+        /// The end of the command's alpha mask, in bytes.
+        end: usize,
+        /// The number of bytes of the alpha masks.
+        len: usize,
+    },
+    /// A clip is popped without a matching push.
+    UnmatchedPopClip {
+        /// The index of the wide tile.
+        wide_tile: usize,
+        /// The index of the command within the wide tile.
+        command: usize,
+    },
+    /// A clip is pushed without a matching pop.
+    UnmatchedPushClip {
+        /// The index of the wide tile.
+        wide_tile: usize,
+    },
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WideTileCountMismatch { expected, actual } => write!(
+                f,
+                "{actual} wide tiles were given, but the render target has {expected} wide tiles"
+            ),
+            Self::OutsideWideTile {
+                wide_tile,
+                command,
+                x,
+                width,
+            } => write!(
+                f,
+                "command {command} of wide tile {wide_tile} spans tiles {x}..{}, beyond the wide \
+                 tile's {} tiles",
+                u32::from(*x) + u32::from(*width),
+                WideTile::WIDTH_TILES
+            ),
+            Self::AlphaMaskOutOfBounds {
+                wide_tile,
+                command,
+                end,
+                len,
+            } => write!(
+                f,
+                "command {command} of wide tile {wide_tile} samples alpha masks up to byte {end}, \
+                 but there are {len} bytes"
+            ),
+            Self::UnmatchedPopClip { wide_tile, command } => write!(
+                f,
+                "command {command} of wide tile {wide_tile} pops a clip that was not pushed"
+            ),
+            Self::UnmatchedPushClip { wide_tile } => {
+                write!(f, "wide tile {wide_tile} pushes a clip that is not popped")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+/// Check that draw commands are well formed for a render target of `width` by `height` pixels,
+/// returning every problem found.
+///
+/// This is useful for commands that are encoded or deserialized by custom code. Commands
+/// generated by [`Bintje`](crate::Bintje) are always well formed. The fallible rasterizers, e.g.,
+/// [`try_cpu_rasterize`](crate::try_cpu_rasterize), always validate the commands before
+/// rasterizing, and [`cpu_rasterize`](crate::cpu_rasterize) does in debug builds.
+pub fn validate_commands(width: u16, height: u16, commands: Commands<'_>) -> Vec<CommandError> {
+    span!("validate_commands", width, height);
+    let mut errors = Vec::new();
+    for_each_command_error(width, height, commands, |err| {
+        errors.push(err);
+        ControlFlow::Continue(())
+    });
+    errors
+}
+
+/// Check that draw commands are well formed like [`validate_commands`], returning the first
+/// problem found rather than collecting every problem.
+///
+/// This stops at the first problem, so it is cheaper than [`validate_commands`] for rejecting
+/// malformed commands, e.g., before rasterizing.
+///
+/// # Errors
+///
+/// Returns the first problem found.
+pub fn check_commands(width: u16, height: u16, commands: Commands<'_>) -> Result<(), CommandError> {
+    span!("check_commands", width, height);
+    let mut result = Ok(());
+    for_each_command_error(width, height, commands, |err| {
+        result = Err(err);
+        ControlFlow::Break(())
+    });
+    result
+}
+
+/// Pass every problem found in the draw commands to `report`, until it breaks.
+fn for_each_command_error(
+    width: u16,
+    height: u16,
+    commands: Commands<'_>,
+    mut report: impl FnMut(CommandError) -> ControlFlow<()>,
+) {
+    let wide_tile_count =
+        width.div_ceil(WIDE_TILE_WIDTH_PX) as usize * height.div_ceil(Tile::HEIGHT) as usize;
+    if commands.wide_tiles.len() != wide_tile_count {
+        let err = CommandError::WideTileCountMismatch {
+            expected: wide_tile_count,
+            actual: commands.wide_tiles.len(),
+        };
+        if report(err).is_break() {
+            return;
+        }
+    }
+
+    let alpha_mask_len = usize::from(Tile::WIDTH) * usize::from(Tile::HEIGHT);
+    for (wide_tile_idx, wide_tile) in commands.wide_tiles.iter().enumerate() {
+        let mut clip_depth = 0_usize;
+        for (command_idx, command) in wide_tile.commands.iter().enumerate() {
+            let (x, width) = match command {
+                Command::Sample(sample) => {
+                    let end =
+                        sample.alpha_idx as usize + usize::from(sample.width) * alpha_mask_len;
+                    if end > commands.alpha_masks.len() {
+                        let err = CommandError::AlphaMaskOutOfBounds {
+                            wide_tile: wide_tile_idx,
+                            command: command_idx,
+                            end,
+                            len: commands.alpha_masks.len(),
+                        };
+                        if report(err).is_break() {
+                            return;
+                        }
+                    }
+                    (sample.x, sample.width)
+                }
+                Command::SparseSample(sparse_sample) => (sparse_sample.x, sparse_sample.width),
+                Command::SparseFill(sparse_fill) => (sparse_fill.x, sparse_fill.width),
+                Command::PushClip(()) => {
+                    clip_depth += 1;
+                    continue;
+                }
+                Command::PopClip(()) => {
+                    if clip_depth == 0 {
+                        let err = CommandError::UnmatchedPopClip {
+                            wide_tile: wide_tile_idx,
+                            command: command_idx,
+                        };
+                        if report(err).is_break() {
+                            return;
+                        }
+                    } else {
+                        clip_depth -= 1;
+                    }
+                    continue;
+                }
+            };
+            if u32::from(x) + u32::from(width) > u32::from(WideTile::WIDTH_TILES) {
+                let err = CommandError::OutsideWideTile {
+                    wide_tile: wide_tile_idx,
+                    command: command_idx,
+                    x,
+                    width,
+                };
+                if report(err).is_break() {
+                    return;
+                }
+            }
+        }
+        if clip_depth > 0
+            && report(CommandError::UnmatchedPushClip {
+                wide_tile: wide_tile_idx,
+            })
+            .is_break()
+        {
+            return;
+        }
+    }
+}
@@ -3,15 +3,21 @@ use peniko::{
     BrushRef,
 };
 
-use crate::{Strip, Tile};
+use crate::{CommandCounts, Damage, Error, Strip, Tile};
 
 /// Number of tiles per wide tile.
-pub(crate) const WIDE_TILE_WIDTH_TILES: u16 = 32;
+///
+/// This is 32, or 64 with the `wide-tile-64` feature.
+pub(crate) const WIDE_TILE_WIDTH_TILES: u16 = if cfg!(feature = "wide-tile-64") {
+    64
+} else {
+    32
+};
 
 /// Number of pixels per wide tile.
 pub(crate) const WIDE_TILE_WIDTH_PX: u16 = Tile::WIDTH * WIDE_TILE_WIDTH_TILES;
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 pub enum Command {
     /// A fill sampling from an alpha mask.
     Sample(Sample),
@@ -27,7 +33,7 @@ pub enum Command {
     PopClip(()),
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 pub struct Sample {
     /// The offset within the wide tile, in tiles.
     pub x: u16,
@@ -36,21 +42,30 @@ pub struct Sample {
     pub color: PremulRgba8,
     /// The index into the global alpha mask, encoding the pixel coverage of the area to be filled.
     pub alpha_idx: u32,
+    /// The caller-supplied ID of the draw that generated this command, see
+    /// [`Bintje::set_draw_id`](crate::Bintje::set_draw_id).
+    pub draw_id: u32,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 pub struct SparseSample {
     pub x: u16,
     pub width: u16,
     pub color: PremulRgba8,
     pub alpha_mask: [u8; Tile::HEIGHT as usize],
+    /// The caller-supplied ID of the draw that generated this command, see
+    /// [`Bintje::set_draw_id`](crate::Bintje::set_draw_id).
+    pub draw_id: u32,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 pub struct SparseFill {
     pub x: u16,
     pub width: u16,
     pub color: PremulRgba8,
+    /// The caller-supplied ID of the draw that generated this command, see
+    /// [`Bintje::set_draw_id`](crate::Bintje::set_draw_id).
+    pub draw_id: u32,
 }
 
 #[derive(Debug)]
@@ -71,13 +86,16 @@ pub(crate) fn generate_wide_tile_commands<'b>(
     wide_tiles: &mut [WideTile],
     strips: &[Strip],
     alpha_masks: &[u8],
+    command_counts: &mut [CommandCounts],
     brush: impl Into<peniko::BrushRef<'b>>,
+    draw_id: u32,
 ) {
     let brush = brush.into();
     let wide_tile_columns = width.div_ceil(WIDE_TILE_WIDTH_PX);
     let wide_tile_rows = (wide_tiles.len() / wide_tile_columns as usize) as u16;
 
     let mut prev_x = 0;
+    let mut prev_y = 0;
 
     for strip in strips.iter().copied() {
         let wide_tile_x = strip.x / WIDE_TILE_WIDTH_TILES;
@@ -87,6 +105,12 @@ pub(crate) fn generate_wide_tile_commands<'b>(
             break;
         }
 
+        // Sparse fills start at the viewport's left edge for the first strip of every row.
+        if wide_tile_y != prev_y {
+            prev_x = 0;
+            prev_y = wide_tile_y;
+        }
+
         let color = match brush {
             BrushRef::Solid(color) => color,
             _ => peniko::color::palette::css::RED,
@@ -116,23 +140,25 @@ pub(crate) fn generate_wide_tile_commands<'b>(
                     WIDE_TILE_WIDTH_TILES
                 };
 
-                let wide_tile = wide_tiles
-                    .get_mut((wide_tile_y * wide_tile_columns + wide_tile_x) as usize)
-                    .unwrap();
-                if fill {
-                    wide_tile.commands.push(Command::SparseFill(SparseFill {
+                let wide_tile_idx = (wide_tile_y * wide_tile_columns + wide_tile_x) as usize;
+                let command = if fill {
+                    Command::SparseFill(SparseFill {
                         x: x_start,
                         width: x_end - x_start,
                         color: color.premultiply().to_rgba8(),
-                    }));
+                        draw_id,
+                    })
                 } else {
-                    wide_tile.commands.push(Command::SparseSample(SparseSample {
+                    Command::SparseSample(SparseSample {
                         x: x_start,
                         width: x_end - x_start,
                         color: color.premultiply().to_rgba8(),
                         alpha_mask: strip.pixel_coverage,
-                    }));
-                }
+                        draw_id,
+                    })
+                };
+                command_counts[wide_tile_idx].count(&command);
+                wide_tiles[wide_tile_idx].commands.push(command);
             }
         }
 
@@ -158,9 +184,7 @@ pub(crate) fn generate_wide_tile_commands<'b>(
                 WIDE_TILE_WIDTH_TILES
             };
 
-            let wide_tile = wide_tiles
-                .get_mut((wide_tile_y * wide_tile_columns + wide_tile_x) as usize)
-                .unwrap();
+            let wide_tile_idx = (wide_tile_y * wide_tile_columns + wide_tile_x) as usize;
 
             let width = x_end - x_start;
             if !alpha_masks[alpha_idx as usize
@@ -169,25 +193,15 @@ pub(crate) fn generate_wide_tile_commands<'b>(
                 .iter()
                 .all(|a| *a == 0)
             {
-                if alpha_masks[alpha_idx as usize
-                    ..alpha_idx as usize
-                        + width as usize * Tile::HEIGHT as usize * Tile::WIDTH as usize]
-                    .iter()
-                    .all(|a| *a == 0)
-                {
-                    wide_tile.commands.push(Command::SparseFill(SparseFill {
-                        x: x_start,
-                        width,
-                        color: color.premultiply().to_rgba8(),
-                    }));
-                } else {
-                    wide_tile.commands.push(Command::Sample(Sample {
-                        x: x_start,
-                        width,
-                        color: color.premultiply().to_rgba8(),
-                        alpha_idx,
-                    }));
-                }
+                let command = Command::Sample(Sample {
+                    x: x_start,
+                    width,
+                    color: color.premultiply().to_rgba8(),
+                    alpha_idx,
+                    draw_id,
+                });
+                command_counts[wide_tile_idx].count(&command);
+                wide_tiles[wide_tile_idx].commands.push(command);
             }
             alpha_idx += width as u32 * Tile::WIDTH as u32 * Tile::HEIGHT as u32;
         }
@@ -197,6 +211,13 @@ pub(crate) fn generate_wide_tile_commands<'b>(
 }
 
 /// CPU rasterization of draw commands to a pixel buffer.
+///
+/// # Panics
+///
+/// Panics if `img` does not have `width * height` pixels, if the number of wide tiles does not
+/// match `width` and `height`, or if the commands are malformed, see
+/// [`validate_commands`](crate::validate_commands). Malformed commands are only detected up front
+/// in debug builds. See [`try_cpu_rasterize`] for a fallible variant.
 pub fn cpu_rasterize(
     width: u16,
     height: u16,
@@ -204,13 +225,141 @@ pub fn cpu_rasterize(
     alpha_masks: &[u8],
     wide_tiles: &[WideTile],
 ) {
+    span!("cpu_rasterize", width, height);
+    // Malformed commands panic while indexing, so validating them is only a debugging aid here.
+    if let Err(err) = rasterize(
+        width,
+        height,
+        img,
+        alpha_masks,
+        wide_tiles,
+        None,
+        cfg!(debug_assertions),
+    ) {
+        panic!("{err}");
+    }
+}
+
+/// CPU rasterization of draw commands to a pixel buffer like [`cpu_rasterize`], returning an error
+/// rather than panicking.
+///
+/// The commands are always validated before rasterizing, which takes time linear in the number of
+/// commands.
+pub fn try_cpu_rasterize(
+    width: u16,
+    height: u16,
+    img: &mut [PremulRgba8],
+    alpha_masks: &[u8],
+    wide_tiles: &[WideTile],
+) -> Result<(), Error> {
+    span!("cpu_rasterize", width, height);
+    rasterize(width, height, img, alpha_masks, wide_tiles, None, true)
+}
+
+/// CPU rasterization of the dirty wide tiles of `damage` to a persistent pixel buffer.
+///
+/// Only the pixels of dirty wide tiles are written. The other pixels of `img` are expected to hold
+/// the previous frame. See [`Bintje::damage`](crate::Bintje::damage).
+///
+/// # Panics
+///
+/// Panics if `img` does not have `width * height` pixels, if the number of wide tiles of
+/// `wide_tiles` or `damage` does not match `width` and `height`, or if the commands are malformed,
+/// like [`cpu_rasterize`]. See [`try_cpu_rasterize_damaged`] for a fallible variant.
+pub fn cpu_rasterize_damaged(
+    width: u16,
+    height: u16,
+    img: &mut [PremulRgba8],
+    alpha_masks: &[u8],
+    wide_tiles: &[WideTile],
+    damage: &Damage,
+) {
+    span!("cpu_rasterize_damaged", width, height);
+    if let Err(err) = rasterize(
+        width,
+        height,
+        img,
+        alpha_masks,
+        wide_tiles,
+        Some(damage.wide_tiles()),
+        cfg!(debug_assertions),
+    ) {
+        panic!("{err}");
+    }
+}
+
+/// CPU rasterization of the dirty wide tiles to a persistent pixel buffer like
+/// [`cpu_rasterize_damaged`], returning an error rather than panicking.
+///
+/// The commands are always validated before rasterizing, like [`try_cpu_rasterize`].
+pub fn try_cpu_rasterize_damaged(
+    width: u16,
+    height: u16,
+    img: &mut [PremulRgba8],
+    alpha_masks: &[u8],
+    wide_tiles: &[WideTile],
+    damage: &Damage,
+) -> Result<(), Error> {
+    span!("cpu_rasterize_damaged", width, height);
+    rasterize(
+        width,
+        height,
+        img,
+        alpha_masks,
+        wide_tiles,
+        Some(damage.wide_tiles()),
+        true,
+    )
+}
+
+/// Rasterize the wide tiles to `img`, skipping wide tiles that are not `dirty` if given, and
+/// rejecting malformed commands up front if `validate` is set.
+fn rasterize(
+    width: u16,
+    height: u16,
+    img: &mut [PremulRgba8],
+    alpha_masks: &[u8],
+    wide_tiles: &[WideTile],
+    dirty: Option<&[bool]>,
+    validate: bool,
+) -> Result<(), Error> {
     const PRINT_CHECKERBOARD: bool = false;
 
-    assert_eq!(img.len(), width as usize * height as usize);
-    assert_eq!(
-        wide_tiles.len(),
-        width.div_ceil(WIDE_TILE_WIDTH_PX) as usize * height.div_ceil(Tile::HEIGHT) as usize
-    );
+    let pixels = width as usize * height as usize;
+    if img.len() != pixels {
+        return Err(Error::ImageSizeMismatch {
+            expected: pixels,
+            actual: img.len(),
+        });
+    }
+    let wide_tile_count =
+        width.div_ceil(WIDE_TILE_WIDTH_PX) as usize * height.div_ceil(Tile::HEIGHT) as usize;
+    if wide_tiles.len() != wide_tile_count {
+        return Err(Error::WideTileCountMismatch {
+            expected: wide_tile_count,
+            actual: wide_tiles.len(),
+        });
+    }
+    // Malformed commands would index out of bounds while rasterizing.
+    if validate {
+        crate::check_commands(
+            width,
+            height,
+            crate::Commands {
+                wide_tiles,
+                alpha_masks,
+            },
+        )
+        .map_err(Error::InvalidCommands)?;
+    }
+    if let Some(dirty) = dirty {
+        if dirty.len() != wide_tile_count {
+            return Err(Error::WideTileCountMismatch {
+                expected: wide_tile_count,
+                actual: dirty.len(),
+            });
+        }
+    }
 
     let wide_tile_rows = height.div_ceil(Tile::HEIGHT);
     let wide_tile_columns = width.div_ceil(WIDE_TILE_WIDTH_PX);
@@ -219,7 +368,11 @@ pub fn cpu_rasterize(
     for wide_tile_y in 0..wide_tile_rows {
         for wide_tile_x in 0..wide_tile_columns {
             let wide_tile = &wide_tiles[wide_tile_idx];
+            let is_dirty = dirty.is_none_or(|dirty| dirty[wide_tile_idx]);
             wide_tile_idx += 1;
+            if !is_dirty {
+                continue;
+            }
 
             let mut scratch =
                 [PremulRgba8::from_u32(0); WIDE_TILE_WIDTH_PX as usize * Tile::HEIGHT as usize];
@@ -329,10 +482,11 @@ pub fn cpu_rasterize(
             }
         }
     }
+    Ok(())
 }
 
 /// Multiply the alpha over a color.
-fn mul_alpha(color: PremulRgba8, alpha: u8) -> PremulRgba8 {
+pub(crate) fn mul_alpha(color: PremulRgba8, alpha: u8) -> PremulRgba8 {
     const COMPOSITE_IN_F32: bool = false;
 
     if COMPOSITE_IN_F32 {
@@ -347,7 +501,7 @@ fn mul_alpha(color: PremulRgba8, alpha: u8) -> PremulRgba8 {
 }
 
 /// Composite one color over another.
-fn over(under: PremulRgba8, over: PremulRgba8) -> PremulRgba8 {
+pub(crate) fn over(under: PremulRgba8, over: PremulRgba8) -> PremulRgba8 {
     const COMPOSITE_IN_F32: bool = false;
 
     if COMPOSITE_IN_F32 {
@@ -10,9 +10,21 @@ use wgpu::util::DeviceExt;
 /// Re-export pollster's `block_on` for convenience.
 pub use pollster::block_on;
 
+#[cfg(test)]
+mod tests;
+
 /// Targetting WebGL2.
 const LIMITS: wgpu::Limits = wgpu::Limits::downlevel_webgl2_defaults();
 
+/// The number of 8-bit values in a draw command's column mask.
+///
+/// This bounds the tile height supported by the draw shader.
+const COLUMN_MASK_LEN: usize = 8;
+const _: () = assert!(
+    bintje::Tile::HEIGHT as usize <= COLUMN_MASK_LEN,
+    "the draw shader supports tiles of at most 8 pixels high"
+);
+
 pub struct RenderContext {
     #[expect(unused, reason = "might come in handy later")]
     instance: wgpu::Instance,
@@ -29,12 +41,54 @@ struct DrawConfig {
     height: u32,
 }
 
+/// An error creating a [`RenderContext`].
+#[derive(Debug)]
+pub enum Error {
+    /// No suitable adapter was found.
+    NoAdapter,
+    /// The device could not be created.
+    RequestDevice(wgpu::RequestDeviceError),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoAdapter => write!(f, "failed to find an adapter"),
+            Self::RequestDevice(_) => write!(f, "failed to find a device"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::NoAdapter => None,
+            Self::RequestDevice(err) => Some(err),
+        }
+    }
+}
+
 impl RenderContext {
+    /// Create a render context on the default adapter.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no adapter or device can be found. See [`RenderContext::try_create`] for a
+    /// fallible variant.
     pub async fn create() -> Self {
+        match Self::try_create().await {
+            Ok(render_context) => render_context,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// Create a render context like [`RenderContext::create`], returning an error rather than
+    /// panicking.
+    pub async fn try_create() -> Result<Self, Error> {
         let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
         let adapter = wgpu::util::initialize_adapter_from_env_or_default(&instance, None)
             .await
-            .expect("would like to get an adapter");
+            .ok_or(Error::NoAdapter)?;
         let (device, queue) = adapter
             .request_device(
                 &wgpu::DeviceDescriptor {
@@ -46,14 +100,14 @@ impl RenderContext {
                 None,
             )
             .await
-            .expect("failed to find a device");
+            .map_err(Error::RequestDevice)?;
 
-        RenderContext {
+        Ok(RenderContext {
             instance,
             adapter,
             device,
             queue,
-        }
+        })
     }
 
     /// Create the actual rasterizer. Currently this only creates the shader required for
@@ -62,6 +116,15 @@ impl RenderContext {
         let draw_shader = self
             .device
             .create_shader_module(wgpu::include_wgsl!("shaders/draw.wgsl"));
+        // The tile dimensions Bintje is configured with are injected into the shader.
+        let draw_shader_constants = std::collections::HashMap::from([
+            ("TILE_WIDTH".to_owned(), bintje::Tile::WIDTH.into()),
+            ("TILE_HEIGHT".to_owned(), bintje::Tile::HEIGHT.into()),
+        ]);
+        let compilation_options = wgpu::PipelineCompilationOptions {
+            constants: &draw_shader_constants,
+            ..Default::default()
+        };
 
         let target_texture = self.device.create_texture(&wgpu::TextureDescriptor {
             label: None,
@@ -155,7 +218,7 @@ impl RenderContext {
                     module: &draw_shader,
                     entry_point: Some("vs"),
                     buffers: &[DrawCmdVertexInstance::buffer_layout()],
-                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                    compilation_options: compilation_options.clone(),
                 },
                 fragment: Some(wgpu::FragmentState {
                     module: &draw_shader,
@@ -167,7 +230,7 @@ impl RenderContext {
                         blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
                         write_mask: wgpu::ColorWrites::ALL,
                     })],
-                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                    compilation_options,
                 }),
                 primitive: wgpu::PrimitiveState {
                     topology: wgpu::PrimitiveTopology::TriangleStrip,
@@ -213,7 +276,7 @@ struct DrawCmdVertexInstance {
     width: u16,
     alpha_idx: u16,
     color: PremulRgba8,
-    column_mask: [u8; bintje::Tile::HEIGHT as usize],
+    column_mask: [u8; COLUMN_MASK_LEN],
 }
 
 impl DrawCmdVertexInstance {
@@ -248,16 +311,22 @@ impl DrawCmdVertexInstance {
                     format: wgpu::VertexFormat::Uint32,
                 },
                 wgpu::VertexAttribute {
-                    offset: std::mem::size_of::<[u16; 6]>()
-                        as wgpu::BufferAddress,
+                    offset: std::mem::size_of::<[u16; 6]>() as wgpu::BufferAddress,
                     shader_location: 5,
-                    format: wgpu::VertexFormat::Unorm8x4,
+                    format: wgpu::VertexFormat::Uint32x2,
                 },
             ],
         }
     }
 }
 
+/// Pad a per-row alpha mask to a draw command column mask.
+fn column_mask(alpha_mask: &[u8; bintje::Tile::HEIGHT as usize]) -> [u8; COLUMN_MASK_LEN] {
+    let mut column_mask = [0; COLUMN_MASK_LEN];
+    column_mask[..alpha_mask.len()].copy_from_slice(alpha_mask);
+    column_mask
+}
+
 pub struct Rasterizer {
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
@@ -340,6 +409,10 @@ impl Rasterizer {
                 occlusion_query_set: None,
                 timestamp_writes: None,
             });
+            if instances.is_empty() {
+                // Only clear the texture.
+                return;
+            }
 
             let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
                 label: None,
@@ -367,7 +440,7 @@ impl Rasterizer {
             render_pass.set_vertex_buffer(
                 0,
                 self.vertex_instance_buffer
-                    .slice(0..(instances.len() * size_of::<DrawCmdVertexInstance>()) as u64),
+                    .slice(0..std::mem::size_of_val(instances) as u64),
             );
             render_pass.set_pipeline(&self.pipeline);
             let mut instance_offset = 0;
@@ -378,7 +451,7 @@ impl Rasterizer {
                     &bind_group,
                     &[step * LIMITS.max_uniform_buffer_binding_size],
                 );
-                render_pass.draw(0..4, instance_offset..next_instance_offset as u32);
+                render_pass.draw(0..4, instance_offset..next_instance_offset);
                 instance_offset = next_instance_offset;
             }
         }
@@ -394,6 +467,54 @@ impl Rasterizer {
         wide_tiles: &[bintje::WideTile],
         width: u16,
         dest_img: &mut [u8],
+    ) {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!("rasterize", width).entered();
+        self.rasterize_wide_tiles(alpha_masks, wide_tiles, width, None, dest_img);
+    }
+
+    /// Rasterize only the dirty wide tiles of `damage`, and copy them to the destination image.
+    ///
+    /// The pixels of wide tiles that are not dirty are left untouched, such that `dest_img` can
+    /// be a persistent target holding the previous frame. See [`bintje::Bintje::damage`].
+    ///
+    /// Returns [`bintje::Error::WideTileCountMismatch`] if `damage` does not have one entry per
+    /// wide tile, in which case nothing is rasterized.
+    pub fn rasterize_damaged(
+        &mut self,
+        alpha_masks: &[u8],
+        wide_tiles: &[bintje::WideTile],
+        width: u16,
+        damage: &bintje::Damage,
+        dest_img: &mut [u8],
+    ) -> Result<(), bintje::Error> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!("rasterize_damaged", width).entered();
+        if damage.wide_tiles().len() != wide_tiles.len() {
+            return Err(bintje::Error::WideTileCountMismatch {
+                expected: wide_tiles.len(),
+                actual: damage.wide_tiles().len(),
+            });
+        }
+        self.rasterize_wide_tiles(
+            alpha_masks,
+            wide_tiles,
+            width,
+            Some(damage.wide_tiles()),
+            dest_img,
+        );
+        Ok(())
+    }
+
+    /// Rasterize the wide tiles, skipping wide tiles that are not `dirty` if given, and copy the
+    /// rasterized wide tiles to the destination image.
+    fn rasterize_wide_tiles(
+        &mut self,
+        alpha_masks: &[u8],
+        wide_tiles: &[bintje::WideTile],
+        width: u16,
+        dirty: Option<&[bool]>,
+        dest_img: &mut [u8],
     ) {
         let t_start = std::time::Instant::now();
         let wide_tiles_per_row = width.div_ceil(bintje::WideTile::WIDTH_PX);
@@ -411,6 +532,10 @@ impl Rasterizer {
         // The uniform-buffer-sized step within the alpha mask buffer.
         let mut alpha_masks_buffer_step = 0;
         let mut alpha_masks_buffer_idx = 0;
+        // The alpha masks uploaded to the alpha mask buffer, by index and width, such that
+        // commands sampling the same alpha mask can share it. Alpha masks are deduplicated by
+        // `bintje::Bintje::compact_alpha_masks`.
+        let mut uploaded_alpha_masks = std::collections::HashMap::new();
         let mut alpha_masks_buffer = self
             .queue
             .write_buffer_with(
@@ -426,6 +551,9 @@ impl Rasterizer {
 
         let mut render_target_cleared = false;
         for (idx, wide_tile) in wide_tiles.iter().enumerate() {
+            if dirty.is_some_and(|dirty| !dirty[idx]) {
+                continue;
+            }
             let wide_tile_y = (idx / wide_tiles_per_row as usize) as u16;
             let wide_tile_x = (idx - (wide_tile_y as usize * wide_tiles_per_row as usize)) as u16;
 
@@ -437,9 +565,18 @@ impl Rasterizer {
                         let alpha_mask_size = sample.width as usize
                             * bintje::Tile::WIDTH as usize
                             * bintje::Tile::HEIGHT as usize;
-                        if alpha_masks_buffer_idx + alpha_mask_size
-                            > (alpha_masks_buffer_step + 1) as usize
-                                * LIMITS.max_uniform_buffer_binding_size as usize
+                        // Reuse the alpha mask if it was uploaded within the current step.
+                        let uploaded = uploaded_alpha_masks
+                            .get(&(sample.alpha_idx, sample.width))
+                            .copied()
+                            .filter(|idx| {
+                                *idx >= alpha_masks_buffer_step as usize
+                                    * LIMITS.max_uniform_buffer_binding_size as usize
+                            });
+                        if uploaded.is_none()
+                            && alpha_masks_buffer_idx + alpha_mask_size
+                                > (alpha_masks_buffer_step + 1) as usize
+                                    * LIMITS.max_uniform_buffer_binding_size as usize
                         {
                             alpha_masks_buffer_step += 1;
                             instance_offsets.push(instances.len() as u32);
@@ -479,19 +616,25 @@ impl Rasterizer {
                                 submits += 1;
                                 instances.clear();
                                 instance_offsets.clear();
+                                uploaded_alpha_masks.clear();
                                 alpha_masks_buffer_step = 0;
                                 alpha_masks_buffer_idx = 0;
                             }
                         }
-                        let alpha_idx = alpha_masks_buffer_idx
-                            % LIMITS.max_uniform_buffer_binding_size as usize;
-                        alpha_masks_buffer
-                            [alpha_masks_buffer_idx..alpha_masks_buffer_idx + alpha_mask_size]
-                            .copy_from_slice(
-                                &alpha_masks[sample.alpha_idx as usize
-                                    ..sample.alpha_idx as usize + alpha_mask_size],
-                            );
-                        alpha_masks_buffer_idx += alpha_mask_size;
+                        let buffer_idx = uploaded.unwrap_or_else(|| {
+                            let buffer_idx = alpha_masks_buffer_idx;
+                            alpha_masks_buffer[buffer_idx..buffer_idx + alpha_mask_size]
+                                .copy_from_slice(
+                                    &alpha_masks[sample.alpha_idx as usize
+                                        ..sample.alpha_idx as usize + alpha_mask_size],
+                                );
+                            uploaded_alpha_masks
+                                .insert((sample.alpha_idx, sample.width), buffer_idx);
+                            alpha_masks_buffer_idx += alpha_mask_size;
+                            buffer_idx
+                        });
+                        let alpha_idx =
+                            buffer_idx % LIMITS.max_uniform_buffer_binding_size as usize;
                         instances.push(DrawCmdVertexInstance {
                             x: (wide_tile_x * bintje::WideTile::WIDTH_TILES + sample.x)
                                 * bintje::Tile::WIDTH,
@@ -500,7 +643,7 @@ impl Rasterizer {
                             color: sample.color,
                             alpha_idx: alpha_idx as u16
                                 / (bintje::Tile::WIDTH * bintje::Tile::HEIGHT),
-                            column_mask: [255; bintje::Tile::HEIGHT as usize],
+                            column_mask: [255; COLUMN_MASK_LEN],
                         });
                     }
                     bintje::Command::SparseSample(sparse_sample) => {
@@ -511,7 +654,7 @@ impl Rasterizer {
                             width: sparse_sample.width * bintje::Tile::WIDTH,
                             color: sparse_sample.color,
                             alpha_idx: u16::MAX,
-                            column_mask: sparse_sample.alpha_mask,
+                            column_mask: column_mask(&sparse_sample.alpha_mask),
                         });
                     }
                     bintje::Command::SparseFill(sparse_fill) => {
@@ -522,14 +665,16 @@ impl Rasterizer {
                             width: sparse_fill.width * bintje::Tile::WIDTH,
                             color: sparse_fill.color,
                             alpha_idx: u16::MAX,
-                            column_mask: [255; bintje::Tile::HEIGHT as usize],
+                            column_mask: [255; COLUMN_MASK_LEN],
                         });
                     }
                     _ => {}
                 }
             }
         }
-        if !instances.is_empty() {
+        // The render target is cleared even if there is nothing to draw, as it may hold the
+        // previous frame.
+        if !instances.is_empty() || !render_target_cleared {
             // Drop the writable alpha mask view, the data is queued for uploading.
             drop(alpha_masks_buffer);
             instance_offsets.push(instances.len() as u32);
@@ -586,14 +731,91 @@ impl Rasterizer {
             });
 
         self.device.poll(wgpu::Maintain::Wait);
+        let row_len = self.width as usize * 4;
+        let wide_tile_len = bintje::WideTile::WIDTH_PX as usize * 4;
         let mut img_idx = 0;
-        for row in (self.texture_copy_buffer.buffer.slice(..).get_mapped_range())
+        for (y, row) in (self.texture_copy_buffer.buffer.slice(..).get_mapped_range())
             .chunks_exact(self.texture_copy_buffer.bytes_per_row as usize)
+            .enumerate()
         {
-            dest_img[img_idx..img_idx + self.width as usize * 4]
-                .copy_from_slice(&row[0..self.width as usize * 4]);
-            img_idx += self.width as usize * 4;
+            match dirty {
+                None => dest_img[img_idx..img_idx + row_len].copy_from_slice(&row[0..row_len]),
+                Some(dirty) => {
+                    // Only copy the wide tiles that were rasterized.
+                    let wide_tile_row = y / bintje::Tile::HEIGHT as usize;
+                    for x in (0..row_len).step_by(wide_tile_len) {
+                        let wide_tile_idx =
+                            wide_tile_row * wide_tiles_per_row as usize + x / wide_tile_len;
+                        if dirty[wide_tile_idx] {
+                            let end = (x + wide_tile_len).min(row_len);
+                            dest_img[img_idx + x..img_idx + end].copy_from_slice(&row[x..end]);
+                        }
+                    }
+                }
+            }
+            img_idx += row_len;
         }
         self.texture_copy_buffer.buffer.unmap();
     }
 }
+
+impl bintje::FineRasterizer for Rasterizer {
+    fn rasterize(
+        &mut self,
+        commands: bintje::Commands<'_>,
+        target: bintje::RenderTarget<'_>,
+    ) -> Result<(), bintje::Error> {
+        // The texture is created for a fixed size.
+        if (target.width, target.height) != (self.width, self.height) {
+            return Err(bintje::Error::UnsupportedTargetSize {
+                width: target.width,
+                height: target.height,
+            });
+        }
+        let pixels = self.width as usize * self.height as usize;
+        if target.pixels.len() != pixels {
+            return Err(bintje::Error::ImageSizeMismatch {
+                expected: pixels,
+                actual: target.pixels.len(),
+            });
+        }
+        let wide_tile_count = self.width.div_ceil(bintje::WideTile::WIDTH_PX) as usize
+            * self.height.div_ceil(bintje::Tile::HEIGHT) as usize;
+        let damage_wide_tiles = target.damage.map(|damage| damage.wide_tiles().len());
+        for actual in [Some(commands.wide_tiles.len()), damage_wide_tiles]
+            .into_iter()
+            .flatten()
+        {
+            if actual != wide_tile_count {
+                return Err(bintje::Error::WideTileCountMismatch {
+                    expected: wide_tile_count,
+                    actual,
+                });
+            }
+        }
+
+        // Malformed commands would index out of bounds while encoding.
+        bintje::check_commands(target.width, target.height, commands)
+            .map_err(bintje::Error::InvalidCommands)?;
+
+        let dest_img = bytemuck::cast_slice_mut(target.pixels);
+        match target.damage {
+            Some(damage) => self.rasterize_damaged(
+                commands.alpha_masks,
+                commands.wide_tiles,
+                target.width,
+                damage,
+                dest_img,
+            )?,
+            // The inherent method, rather than this trait method.
+            None => Self::rasterize(
+                self,
+                commands.alpha_masks,
+                commands.wide_tiles,
+                target.width,
+                dest_img,
+            ),
+        }
+        Ok(())
+    }
+}
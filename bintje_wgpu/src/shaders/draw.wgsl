@@ -1,11 +1,11 @@
 // This shader takes the wide tile commands (and their positions) as vertex
 // instance data. The vertex buffer steps per index.
 
-// The shader is not truly generic over the values here, as the layout of and
-// indexing into `alpha_masks` is directly influenced by it. They're named as
-// constants mostly for clarity.
-const TILE_WIDTH: u32 = 4;
-const TILE_HEIGHT: u32 = 4;
+// The tile dimensions in pixels. These determine the layout of `alpha_masks`
+// and the column masks, and are overridden by the rasterizer with the
+// dimensions Bintje is configured with. Tiles can be at most 8 pixels high.
+override TILE_WIDTH: u32 = 4;
+override TILE_HEIGHT: u32 = 4;
 
 // A draw command strip.
 struct Instance {
@@ -20,8 +20,9 @@ struct Instance {
     @location(3) alpha_idx: u32,
     // The color to draw.
     @location(4) color: u32,
-    // An alpha mask vector to be applied to the columns.
-    @location(5) column_mask: vec4<f32>,
+    // An alpha mask vector to be applied to the columns, packed as 8-bit
+    // values. Only the first `TILE_HEIGHT` values are used.
+    @location(5) column_mask: vec2<u32>,
 }
 
 struct VertexOutput {
@@ -32,8 +33,9 @@ struct VertexOutput {
     // The starting index into the alpha mask buffer. If this is 0xffff, the
     // draw is a fill.
     @location(1) alpha_idx: u32,
-    // An alpha mask vector to be applied to the columns.
-    @location(2) column_mask: vec4<f32>,
+    // An alpha mask vector to be applied to the columns, packed as 8-bit
+    // values.
+    @location(2) column_mask: vec2<u32>,
     // The draw command origin x-coordinate in pixels.
     @location(3) x: u32,
     // The draw command origin y-coordinate in pixels.
@@ -89,10 +91,18 @@ fn fs(in: VertexOutput) -> FragOut {
     let in_y = floor(in.pos.y);
 
     var output: FragOut;
-    let alpha_idx = in.alpha_idx + (u32(in_x) - in.x) / TILE_WIDTH;
-    let alpha_mask = unpack4x8unorm(alpha_masks[alpha_idx][u32(in_x) % TILE_WIDTH]);
+    // The alpha masks are stored per tile, with the pixels of a tile in
+    // column-major order. Four 8-bit alpha values are packed per `u32`.
+    let row = u32(in_y) - in.y;
+    let alpha_byte_idx = in.alpha_idx * TILE_WIDTH * TILE_HEIGHT
+        + (u32(in_x) - in.x) * TILE_HEIGHT
+        + row;
+    let alpha_mask = unpack4x8unorm(
+        alpha_masks[alpha_byte_idx / 16][(alpha_byte_idx / 4) % 4]
+    )[alpha_byte_idx % 4];
+    let column_mask = unpack4x8unorm(in.column_mask[row / 4])[row % 4];
     output.color = in.color
-        * in.column_mask[u32(in_y) % TILE_HEIGHT]
-        * (f32(in.alpha_idx == 0xffff) + f32(in.alpha_idx != 0xffff) * alpha_mask[u32(in_y) - in.y]);
+        * column_mask
+        * (f32(in.alpha_idx == 0xffff) + f32(in.alpha_idx != 0xffff) * alpha_mask);
     return output;
 }
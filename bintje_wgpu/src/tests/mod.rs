@@ -0,0 +1,18 @@
+use bintje::testing::{check_rasterizer, TARGET_HEIGHT, TARGET_WIDTH};
+
+use crate::{block_on, RenderContext};
+
+#[test]
+fn fine_rasterizer() {
+    // Not every machine running the tests has an adapter.
+    let mut render_context = match block_on(RenderContext::try_create()) {
+        Ok(render_context) => render_context,
+        Err(err) => {
+            eprintln!("Skipping the wgpu rasterizer checks: {err}");
+            return;
+        }
+    };
+    let mut rasterizer = render_context.rasterizer(TARGET_WIDTH, TARGET_HEIGHT);
+    // The GPU rounds when blending, where `cpu_rasterize` truncates.
+    check_rasterizer(&mut rasterizer, 1);
+}
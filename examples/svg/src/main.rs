@@ -7,12 +7,16 @@ use kurbo::Affine;
 use peniko::color::{self, PremulRgba8};
 use pico_svg::Item;
 
-use bintje::{cpu_rasterize, Bintje};
+use bintje::{Bintje, CpuRasterizer, DebugRasterizer, FineRasterizer, RenderStats, RenderTarget};
 use bintje_wgpu::RenderContext;
 
 pub mod pico_svg;
 
 /// Render an SVG.
+///
+/// Pass `--cpu` to rasterize on the CPU rather than using wgpu. Pass `--debug` to also write
+/// `test-debug.png`, visualizing the wide tiles and commands, see [`DebugRasterizer`]. Pass
+/// `--bands` to also stream `test-bands.png` to disk band by band, see [`bintje::write_bands`].
 pub fn main() {
     let scale = 1.;
     let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../assets/tiger/Ghostscript_Tiger.svg");
@@ -29,34 +33,40 @@ pub fn main() {
     );
     let (width, height) = renderer.size();
 
-    let mut gpu_render_context = bintje_wgpu::block_on(RenderContext::create());
-    let mut fragment_shader = gpu_render_context.rasterizer(width, height);
+    let mut gpu_rasterizer = (!std::env::args().any(|arg| arg == "--cpu")).then(|| {
+        let mut gpu_render_context = bintje_wgpu::block_on(RenderContext::create());
+        gpu_render_context.rasterizer(width, height)
+    });
+    let rasterizer: &mut dyn FineRasterizer = match &mut gpu_rasterizer {
+        Some(gpu_rasterizer) => gpu_rasterizer,
+        None => &mut CpuRasterizer,
+    };
 
     let mut img = vec![PremulRgba8::from_u32(0); width as usize * height as usize];
     let now = std::time::Instant::now();
     let mut coarse = std::time::Duration::ZERO;
     let mut fine = std::time::Duration::ZERO;
+    let mut stats = RenderStats::default();
     const NUM_ITERATIONS: u16 = 100;
     for _ in 0..NUM_ITERATIONS {
         renderer.clear();
         let mut start = std::time::Instant::now();
         encode_svg(&mut renderer, 1. / scale, Affine::IDENTITY, &svg.items);
+        renderer.compact_alpha_masks();
         coarse += start.elapsed();
+        add_timings(&mut stats, renderer.stats());
         start = std::time::Instant::now();
-        let commands = renderer.commands();
-        // cpu_rasterize(
-        //     width,
-        //     height,
-        //     &mut img,
-        //     commands.alpha_masks,
-        //     commands.wide_tiles,
-        // );
-        fragment_shader.rasterize(
-            commands.alpha_masks,
-            commands.wide_tiles,
-            width,
-            bytemuck::cast_slice_mut(&mut img),
-        );
+        rasterizer
+            .rasterize(
+                renderer.commands(),
+                RenderTarget {
+                    width,
+                    height,
+                    pixels: &mut img,
+                    damage: None,
+                },
+            )
+            .unwrap();
         fine += start.elapsed();
     }
     println!(
@@ -69,51 +79,163 @@ pub fn main() {
     );
     println!(
         " - Elapsed flattening:          {:?}ms",
-        renderer.flattening_time.as_nanos() as f32 / (NUM_ITERATIONS as f32 * 1_000_000.)
+        stats.flattening_time.as_nanos() as f32 / (NUM_ITERATIONS as f32 * 1_000_000.)
     );
     println!(
         " - Elapsed flattening (stroke): {:?}ms",
-        renderer.flattening_stroke_time.as_nanos() as f32 / (NUM_ITERATIONS as f32 * 1_000_000.)
+        stats.flattening_stroke_time.as_nanos() as f32 / (NUM_ITERATIONS as f32 * 1_000_000.)
     );
     println!(
         " - Tile generation elapsed:     {:?}ms",
-        renderer.tile_generation_time.as_nanos() as f32 / (NUM_ITERATIONS as f32 * 1_000_000.)
+        stats.tile_generation_time.as_nanos() as f32 / (NUM_ITERATIONS as f32 * 1_000_000.)
     );
     println!(
         " - Tile sorting elapsed:        {:?}ms",
-        renderer.tile_sorting_time.as_nanos() as f32 / (NUM_ITERATIONS as f32 * 1_000_000.)
+        stats.tile_sorting_time.as_nanos() as f32 / (NUM_ITERATIONS as f32 * 1_000_000.)
     );
     println!(
         " - Strip generation elapsed:    {:?}ms",
-        renderer.strip_generation_time.as_nanos() as f32 / (NUM_ITERATIONS as f32 * 1_000_000.)
+        stats.strip_generation_time.as_nanos() as f32 / (NUM_ITERATIONS as f32 * 1_000_000.)
     );
     println!(
         "Fine elapsed:                   {:?}ms",
         fine.as_nanos() as f32 / (NUM_ITERATIONS as f32 * 1_000_000.)
     );
+    if let Some(gpu_rasterizer) = &gpu_rasterizer {
+        println!(
+            " - GPU minus copy out:          {:?}ms",
+            gpu_rasterizer.fine_time.as_nanos() as f32 / (NUM_ITERATIONS as f32 * 1_000_000.)
+        );
+    }
+
+    if std::env::args().any(|arg| arg == "--debug") {
+        let mut debug_img = vec![PremulRgba8::from_u32(0); width as usize * height as usize];
+        DebugRasterizer::default()
+            .rasterize(
+                renderer.commands(),
+                RenderTarget {
+                    width,
+                    height,
+                    pixels: &mut debug_img,
+                    damage: None,
+                },
+            )
+            .unwrap();
+        write_png("test-debug.png", &mut debug_img, width, height);
+    }
+
+    if std::env::args().any(|arg| arg == "--bands") {
+        let file = std::fs::File::create("test-bands.png").unwrap();
+        let mut encoder =
+            png::Encoder::new(std::io::BufWriter::new(file), width.into(), height.into());
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        let mut stream = writer.stream_writer().unwrap();
+        let start = std::time::Instant::now();
+        bintje::write_bands(
+            width,
+            height,
+            |band_renderer, _| encode_svg(band_renderer, 1. / scale, Affine::IDENTITY, &svg.items),
+            &mut stream,
+        )
+        .unwrap();
+        stream.finish().unwrap();
+        println!(
+            "Banded PNG elapsed:             {:?}ms",
+            start.elapsed().as_nanos() as f32 / 1_000_000.
+        );
+    }
+
+    let frame_stats = renderer.stats();
+    let commands = frame_stats.commands();
+    println!("Per frame:");
+    println!(" - Lines:                        {}", frame_stats.lines);
+    println!(" - Tiles:                        {}", frame_stats.tiles);
+    println!(" - Strips:                       {}", frame_stats.strips);
+    println!(
+        " - Alpha mask bytes:             {}",
+        frame_stats.alpha_mask_bytes
+    );
     println!(
-        " - GPU minus copy out:          {:?}ms",
-        fragment_shader.fine_time.as_nanos() as f32 / (NUM_ITERATIONS as f32 * 1_000_000.)
+        " - Compacted alpha mask bytes:   {}",
+        renderer.commands().alpha_masks.len()
     );
+    println!(
+        " - Culled:                       {} draws, {} subpaths",
+        frame_stats.culled_draws, frame_stats.culled_subpaths
+    );
+    println!(
+        " - Commands:                     {} ({} sample, {} sparse sample, {} sparse fill)",
+        commands.total(),
+        commands.sample,
+        commands.sparse_sample,
+        commands.sparse_fill,
+    );
+    println!(
+        " - Max commands per wide tile:   {}",
+        frame_stats
+            .wide_tile_commands
+            .iter()
+            .map(|counts| counts.total())
+            .max()
+            .unwrap_or(0)
+    );
+
+    // Measure the effect of the flattening tolerance on coarse rasterization.
+    println!("Flattening tolerance:");
+    for tolerance in [0.1, Bintje::DEFAULT_TOLERANCE, 0.5, 1.0, 2.0] {
+        renderer.set_tolerance(tolerance);
+        let mut tolerance_stats = RenderStats::default();
+        let start = std::time::Instant::now();
+        for _ in 0..NUM_ITERATIONS {
+            renderer.clear();
+            encode_svg(&mut renderer, 1. / scale, Affine::IDENTITY, &svg.items);
+            add_timings(&mut tolerance_stats, renderer.stats());
+        }
+        let elapsed = start.elapsed();
+        println!(
+            " - {tolerance:<4}px: coarse {:?}ms, flattening {:?}ms, flattening (stroke) {:?}ms",
+            elapsed.as_nanos() as f32 / (NUM_ITERATIONS as f32 * 1_000_000.),
+            tolerance_stats.flattening_time.as_nanos() as f32
+                / (NUM_ITERATIONS as f32 * 1_000_000.),
+            tolerance_stats.flattening_stroke_time.as_nanos() as f32
+                / (NUM_ITERATIONS as f32 * 1_000_000.),
+        );
+    }
 
-    unpremultiply(&mut img);
+    write_png("test.png", &mut img, width, height);
+}
+
+/// Unpremultiply the pixels and write them to a PNG file.
+fn write_png(path: &str, img: &mut [PremulRgba8], width: u16, height: u16) {
+    unpremultiply(img);
     let file = std::fs::OpenOptions::new()
         .create(true)
         .truncate(true)
         .write(true)
-        .open("test.png")
+        .open(path)
         .unwrap();
     let encoder = image::codecs::png::PngEncoder::new(file);
     encoder
         .write_image(
-            bytemuck::cast_slice(&img),
-            width as u32,
-            height as u32,
+            bytemuck::cast_slice(img),
+            width.into(),
+            height.into(),
             image::ExtendedColorType::Rgba8,
         )
         .unwrap();
 }
 
+/// Add the timings of a frame's statistics to the accumulated statistics.
+fn add_timings(accumulated: &mut RenderStats, frame: &RenderStats) {
+    accumulated.flattening_time += frame.flattening_time;
+    accumulated.flattening_stroke_time += frame.flattening_stroke_time;
+    accumulated.tile_generation_time += frame.tile_generation_time;
+    accumulated.tile_sorting_time += frame.tile_sorting_time;
+    accumulated.strip_generation_time += frame.strip_generation_time;
+}
+
 fn unpremultiply(premultiplied: &mut [PremulRgba8]) {
     for color in premultiplied {
         let f_color = color::PremulColor::<color::Srgb>::from(*color);